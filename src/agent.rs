@@ -0,0 +1,267 @@
+use crate::datastructures::*;
+use crate::difficulty::Difficulty;
+use crate::mcts::*;
+use crate::notation::*;
+use crate::rng::Rng;
+use crate::search::*;
+use crate::transposition::TranspositionTable;
+use crate::variant::Variant;
+use std::io;
+use std::time::Duration;
+
+// what an agent wants to do on its turn
+pub enum Action {
+    // the complete move, including the stone to take
+    Play(MillMove),
+    // write the game to the given file and ask again
+    Save(String),
+    // show the move the engine recommends with its reasons and ask again
+    Hint,
+    // take back the last move of the player, or play it again
+    Undo,
+    Redo,
+    GiveUp,
+}
+
+impl From<Option<MillMove>> for Action {
+    fn from(millmove: Option<MillMove>) -> Action {
+        millmove.map_or(Action::GiveUp, Action::Play)
+    }
+}
+
+// anything that can decide on the moves of one side of a MillGame
+pub trait Agent {
+    fn name(&self) -> String;
+    fn choose_move(&mut self, gameboard: &GameBoard) -> Action;
+    // true if the agent talks to a person on the terminal
+    fn is_interactive(&self) -> bool {
+        false
+    }
+    // true if the strength of the agent can be set with a Difficulty
+    fn has_levels(&self) -> bool {
+        false
+    }
+    fn set_difficulty(&mut self, _difficulty: Difficulty) {}
+}
+
+// creates an agent by its name as used on the command line, the engine searches with the weights and
+// endgame tables of the base limits. the budget of the tree search can be given as "mcts:<iterations>"
+// or "mcts:<milliseconds>ms", "mcts-random" plays random instead of heuristic rollouts
+pub fn agent_from_name(name: &str, base: &SearchLimits) -> Option<Box<dyn Agent>> {
+    let (name, budget) = match name.split_once(':') {
+        Some((name, budget)) => (name, Some(budget)),
+        None => (name, None),
+    };
+    match (name, budget) {
+        ("human", None) => Some(Box::new(HumanAgent)),
+        ("random", None) => Some(Box::new(RandomAgent::new(Rng::from_time()))),
+        ("engine", None) => Some(Box::new(EngineAgent::new(SearchLimits { depth: Some(5), ..base.clone() }))),
+        ("mcts" | "mcts-random", budget) => {
+            let rollout = if name == "mcts" { Rollout::Heuristic } else { Rollout::Random };
+            let mut limits = MctsLimits { rollout, weights: base.weights, ..MctsLimits::default() };
+            match budget.map(|budget| budget.strip_suffix("ms").map_or((budget, false), |millis| (millis, true))) {
+                Some((millis, true)) => {
+                    limits.time = Some(Duration::from_millis(millis.parse().ok()?));
+                    limits.iterations = None;
+                }
+                Some((iterations, false)) => limits.iterations = Some(iterations.parse().ok()?),
+                None => {}
+            }
+            Some(Box::new(MctsAgent::new(limits, Rng::from_time())))
+        }
+        _ => None,
+    }
+}
+
+// a person entering fields or moves on the terminal
+pub struct HumanAgent;
+
+impl Agent for HumanAgent {
+    fn name(&self) -> String {
+        String::from("human")
+    }
+
+    fn choose_move(&mut self, gameboard: &GameBoard) -> Action {
+        let turn = gameboard.turn;
+        if gameboard.pending_removals > 0 {
+            return take_opponent_stone(gameboard).map(|stone| MillMove::removal(turn, gameboard, stone)).into()
+        }
+        loop {
+            match gameboard.gamephase {
+                Phase::Place => println!("Please enter where you want to put your stone."),
+                Phase::Move => println!("Please enter which stone you want to move (or the whole move, e.g. a1-a4)."),
+            }
+            println!("Type 'undo' or 'redo' to take back moves, 'save <file>' to save the game and continue it later with 'play --resume <file>'.");
+            println!("Type 'hint' to see which move the engine recommends and why.");
+            let Some(input) = get_user_input() else { return Action::GiveUp };
+            if let Some(path) = input.strip_prefix("save ") {
+                return Action::Save(path.trim().to_string())
+            }
+            match input.to_ascii_lowercase().as_str() {
+                "undo" => return Action::Undo,
+                "redo" => return Action::Redo,
+                "hint" => return Action::Hint,
+                _ => {}
+            }
+            //either a whole move like "d2", "a1-a4" or "g7xd1" or just the stone to move
+            let mut millmove = match (MillMove::from_notation(&input, gameboard), parse_field(&input, gameboard.variant)) {
+                (Some(millmove), _) => millmove,
+                (None, Some(origin)) if gameboard.gamephase == Phase::Move => {
+                    println!("Please enter where you want to move your stone on field {}", field_name(origin, gameboard.variant));
+                    let destination = get_user_input_as_field(gameboard.variant);
+                    if destination == 0 {
+                        return Action::GiveUp
+                    }
+                    MillMove::new(turn, gameboard, origin, destination)
+                }
+                _ => {
+                    println!("Invalid Input. Please try again.");
+                    continue;
+                }
+            };
+            if let Err(err) = millmove.validate(gameboard) {
+                println!("Invalid move: {}. Please try again.", err);
+                continue;
+            }
+            //a closed mill takes an opponent stone with the same move
+            if millmove.remove.is_none() && millmove.needs_removal(gameboard) {
+                let Some(stone) = take_opponent_stone(gameboard) else { return Action::GiveUp };
+                millmove.remove = Some(stone);
+            }
+            return Action::Play(millmove)
+        }
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+// retrieves input from player, which stone he wants to take after closing a mill
+fn take_opponent_stone(gameboard: &GameBoard) -> Option<u8> {
+    let current_turn = gameboard.turn;
+    println!("You closed a Mill! Which stone do you want to delete?");
+    loop {
+        let index = get_user_input_as_field(gameboard.variant);
+        if index == 0 {
+            return None
+        }
+        match gameboard.stone_at(index) {
+            None => {
+                println!("This field is empty! Please try again.");
+            }
+            Some(player) if player == current_turn => {
+                println!("Stone must be from the opponent! Please try again.");
+            }
+            Some(_) => {
+                if gameboard.get_takeable_stones(current_turn).contains(&index) {
+                    return Some(index)
+                }
+                println!("Can't take stone, because it is part of a mill! Please try again.");
+            }
+        }
+    }
+}
+
+// reads one line from the terminal, None if the player wants to give up
+fn get_user_input() -> Option<String> {
+    println!("Please enter a valid field (e.g. d2 or its number) to continue or type either 'exit' or '0' to give up and end the game.");
+    let mut user_input = String::new();
+    //closed input counts as giving up
+    match io::stdin().read_line(&mut user_input) {
+        Ok(0) | Err(_) => return None,
+        Ok(_) => {}
+    }
+    let cleaned_input = user_input.trim();
+    if cleaned_input.eq_ignore_ascii_case("exit") || cleaned_input == "0" {
+        return None
+    }
+    Some(cleaned_input.to_string())
+}
+
+// reads a single field, 0 if the player wants to give up
+fn get_user_input_as_field(variant: Variant) -> u8 {
+    loop {
+        let Some(input) = get_user_input() else { return 0 };
+        match parse_field(&input, variant) {
+            Some(index) => return index,
+            None => println!("Invalid Input. Please try again."),
+        }
+    }
+}
+
+// plays a uniformly random legal move
+pub struct RandomAgent {
+    rng: Rng,
+}
+
+impl RandomAgent {
+    pub fn new(rng: Rng) -> RandomAgent {
+        RandomAgent { rng }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn name(&self) -> String {
+        String::from("random")
+    }
+
+    fn choose_move(&mut self, gameboard: &GameBoard) -> Action {
+        let turns = gameboard.possible_turns(gameboard.turn);
+        if turns.is_empty() {
+            return Action::GiveUp
+        }
+        Action::Play(turns[self.rng.below(turns.len())])
+    }
+}
+
+// plays the best move found by the alpha-beta search, the table is kept for the whole game.
+// on the lower levels some moves are played at random instead
+pub struct EngineAgent {
+    // the limits the agent was created with, a level takes weights and endgame tables from them
+    base: SearchLimits,
+    limits: SearchLimits,
+    table: TranspositionTable,
+    rng: Rng,
+    mistake_percent: usize,
+}
+
+impl EngineAgent {
+    pub fn new(limits: SearchLimits) -> EngineAgent {
+        EngineAgent { base: limits.clone(), limits, table: TranspositionTable::default(), rng: Rng::from_time(), mistake_percent: 0 }
+    }
+
+    pub fn with_difficulty(difficulty: Difficulty, base: SearchLimits) -> EngineAgent {
+        let mut agent = EngineAgent::new(base);
+        agent.set_difficulty(difficulty);
+        agent
+    }
+}
+
+impl Agent for EngineAgent {
+    fn name(&self) -> String {
+        String::from("engine")
+    }
+
+    fn choose_move(&mut self, gameboard: &GameBoard) -> Action {
+        let Some(best) = search(gameboard, gameboard.turn, self.limits.clone(), &mut self.table, |_| {}).map(|result| result.millmove) else {
+            return Action::GiveUp
+        };
+        if self.mistake_percent > 0 && self.rng.below(100) < self.mistake_percent {
+            let others: Vec<MillMove> = gameboard.possible_turns(gameboard.turn).into_iter().filter(|millmove| (millmove.origin, millmove.destination, millmove.remove) != (best.origin, best.destination, best.remove)).collect();
+            if !others.is_empty() {
+                return Action::Play(others[self.rng.below(others.len())])
+            }
+        }
+        Action::Play(best)
+    }
+
+    fn has_levels(&self) -> bool {
+        true
+    }
+
+    fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.limits = difficulty.limits(&self.base);
+        self.mistake_percent = difficulty.mistake_percent();
+    }
+}
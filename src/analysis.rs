@@ -0,0 +1,405 @@
+use crate::datastructures::*;
+use crate::evaluation::Weights;
+use crate::notation::*;
+use crate::search::*;
+use crate::transposition::TranspositionTable;
+use crate::variant::Variant;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+// counts of a player's options in a position: moves, moves closing a mill and opponent stones that could be taken
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MoveCounts {
+    pub moves: u8,
+    pub mills: u8,
+    pub takeable: u8,
+}
+
+impl MoveCounts {
+    // while placing the placements are counted, in the move phase the moves of the stones on the board
+    pub fn of(gameboard: &GameBoard, player: Player) -> MoveCounts {
+        if gameboard.gamephase == Phase::Move {
+            return MoveCounts {
+                moves: gameboard.possible_moves_amount(player),
+                mills: gameboard.possible_mill_amount(player),
+                takeable: gameboard.takeable_opponent_amount(player),
+            }
+        }
+        let steps = distinct_steps(gameboard, player);
+        let mills = steps.iter().filter(|millmove| millmove.closes_mill(gameboard)).count() as u8;
+        MoveCounts {
+            moves: steps.len() as u8,
+            mills,
+            takeable: if mills == 0 { 0 } else { gameboard.get_takeable_stones(player).len() as u8 },
+        }
+    }
+}
+
+// the moves of the player without the stone they take, a move closing a mill is listed once
+fn distinct_steps(gameboard: &GameBoard, player: Player) -> Vec<MillMove> {
+    let mut steps: Vec<MillMove> = Vec::new();
+    for millmove in gameboard.possible_turns(player).iter().filter(|millmove| !millmove.is_removal()) {
+        let step = MillMove { remove: None, ..*millmove };
+        if !steps.iter().any(|known| known.origin == step.origin && known.destination == step.destination) {
+            steps.push(step);
+        }
+    }
+    steps
+}
+
+// everything known about one position of the input, the moves are those of the player to move
+#[derive(Clone)]
+pub struct PositionReport {
+    pub line: usize,
+    pub position: String,
+    pub variant: Variant,
+    pub turn: Player,
+    pub white: MoveCounts,
+    pub black: MoveCounts,
+    pub legal_moves: Vec<MillMove>,
+    // the moves closing a mill, without the stone they take
+    pub mill_moves: Vec<MillMove>,
+    // the opponent stones the player to move may take
+    pub removable: Vec<u8>,
+    pub evaluation: Option<SearchResult>,
+}
+
+impl PositionReport {
+    // the evaluation is the result of a search of the position, if it was searched
+    pub fn new(line: usize, gameboard: &GameBoard, evaluation: Option<SearchResult>) -> PositionReport {
+        let turn = gameboard.turn;
+        let legal_moves = gameboard.possible_turns(turn);
+        let mill_moves: Vec<MillMove> = distinct_steps(gameboard, turn).into_iter().filter(|millmove| millmove.closes_mill(gameboard)).collect();
+        PositionReport {
+            line,
+            position: gameboard.to_position(),
+            variant: gameboard.variant,
+            turn,
+            white: MoveCounts::of(gameboard, Player::White),
+            black: MoveCounts::of(gameboard, Player::Black),
+            removable: gameboard.get_takeable_stones(turn),
+            evaluation,
+            legal_moves,
+            mill_moves,
+        }
+    }
+}
+
+// how the results are written: the three counts of the perspective, one JSON object per line or CSV with a header
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    JsonLines,
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" => Some(OutputFormat::Text),
+            "jsonl" | "json" => Some(OutputFormat::JsonLines),
+            "csv" => Some(OutputFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+const CSV_HEADER: &str = "line,position,turn,white_moves,white_mills,white_takeable,black_moves,black_mills,black_takeable,legal_moves,mill_moves,removable,score,best_move";
+
+// where the positions come from, where the results go and whose options are counted,
+// without a file stdin and stdout are used
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnalysisOptions {
+    pub input: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub perspective: Player,
+    pub format: OutputFormat,
+    // the depth of the search for the evaluation and best move, nothing is searched without it
+    pub evaluation_depth: Option<u8>,
+    pub weights: Weights,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        AnalysisOptions { input: None, output: None, perspective: Player::White, format: OutputFormat::Text, evaluation_depth: None, weights: Weights::default() }
+    }
+}
+
+impl AnalysisOptions {
+    // reads [--input <file>] [--output <file>] [--perspective white|black] [--format text|jsonl|csv] [--evaluate <depth>] [--weights <file>],
+    // "-" stands for stdin or stdout
+    pub fn parse(args: &[String]) -> Result<AnalysisOptions, String> {
+        let mut options = AnalysisOptions::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+            let path = |value: &String| (value != "-").then(|| PathBuf::from(value));
+            match arg.as_str() {
+                "--input" | "-i" => options.input = path(value()?),
+                "--output" | "-o" => options.output = path(value()?),
+                "--perspective" | "-p" => {
+                    options.perspective = match value()?.to_ascii_lowercase().as_str() {
+                        "white" | "w" => Player::White,
+                        "black" | "b" => Player::Black,
+                        other => return Err(format!("unknown perspective '{}', choose between white and black", other)),
+                    }
+                }
+                "--format" | "-f" => {
+                    let name = value()?;
+                    options.format = OutputFormat::from_name(name).ok_or_else(|| format!("unknown format '{}', choose between text, jsonl and csv", name))?;
+                }
+                "--evaluate" | "-e" => {
+                    let depth = value()?;
+                    let depth = depth.parse::<u8>().ok().filter(|depth| (1..=MAX_DEPTH).contains(depth));
+                    options.evaluation_depth = Some(depth.ok_or_else(|| format!("the evaluation depth has to be between 1 and {}", MAX_DEPTH))?);
+                }
+                "--weights" | "-w" => {
+                    let path = value()?;
+                    options.weights = Weights::load(Path::new(path)).map_err(|err| format!("error loading the weights from {}: {}", path, err))?;
+                }
+                other => return Err(format!("unknown argument '{}'", other)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// a line of the input that isn't a position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidLine {
+    pub line: usize,
+    pub error: PositionError,
+}
+
+impl fmt::Display for InvalidLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+// the analyze command of the binaries: parses the options of AnalysisOptions::parse and analyses the input.
+// malformed lines are reported with their number on stderr and make the exit code non-zero
+pub fn main(args: &[String]) -> ExitCode {
+    let options = match AnalysisOptions::parse(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE
+        }
+    };
+    //every malformed line is reported as soon as it is read, not after the end of the input
+    match run_analysis(&options, |line| eprintln!("invalid position in {}", line)) {
+        Ok(invalid) if invalid.is_empty() => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("Error processing the positions: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// analyses the input given by the options into their output, see analyze_positions
+pub fn run_analysis(options: &AnalysisOptions, on_invalid: impl FnMut(&InvalidLine)) -> io::Result<Vec<InvalidLine>> {
+    let input: Box<dyn BufRead> = match &options.input {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(io::stdin().lock()),
+    };
+    let output: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        //stdout is line buffered, every result shows up as soon as its line is analysed
+        None => Box::new(io::stdout().lock()),
+    };
+    analyze_positions(input, output, options, on_invalid)
+}
+
+// reads one position string per line and writes the report of each in the format of the options.
+// lines that aren't positions are passed to on_invalid when they are read and returned with their number.
+// the text format has no line numbers, so empty and invalid lines get a row of "0 0 0" there to keep the rows
+// in line with the input, the other formats skip them
+pub fn analyze_positions(input: impl BufRead, mut output: impl Write, options: &AnalysisOptions, mut on_invalid: impl FnMut(&InvalidLine)) -> io::Result<Vec<InvalidLine>> {
+    let mut invalid = Vec::new();
+    //one table for all searches, cleared for every position so that each is searched like on its own
+    let mut table = options.evaluation_depth.map(|_| TranspositionTable::default());
+    if options.format == OutputFormat::Csv {
+        writeln!(output, "{}", CSV_HEADER)?;
+    }
+    for (number, line) in input.lines().enumerate() {
+        let line = line?;
+        let parsed = (!line.trim().is_empty()).then(|| line.trim().parse::<GameBoard>());
+        match parsed {
+            Some(Ok(gameboard)) => {
+                let evaluation = options.evaluation_depth.zip(table.as_mut()).and_then(|(depth, table)| {
+                    table.clear();
+                    let limits = SearchLimits { weights: options.weights, ..SearchLimits::depth(depth) };
+                    search(&gameboard, gameboard.turn, limits, table, |_| {})
+                });
+                let report = PositionReport::new(number + 1, &gameboard, evaluation);
+                write_report(&mut output, &report, options)?;
+                continue;
+            }
+            Some(Err(error)) => {
+                let line = InvalidLine { line: number + 1, error };
+                on_invalid(&line);
+                invalid.push(line);
+            }
+            None => {}
+        }
+        if options.format == OutputFormat::Text {
+            writeln!(output, "0 0 0")?;
+        }
+    }
+    output.flush()?;
+    Ok(invalid)
+}
+
+fn write_report(output: &mut impl Write, report: &PositionReport, options: &AnalysisOptions) -> io::Result<()> {
+    let names = |moves: &[MillMove]| moves.iter().map(|millmove| millmove.to_string()).collect::<Vec<String>>();
+    let (legal_moves, mill_moves, removable) = (names(&report.legal_moves), names(&report.mill_moves), report.removable.iter().map(|stone| field_name(*stone, report.variant)).collect::<Vec<String>>());
+    let turn = match report.turn {
+        Player::White => "white",
+        Player::Black => "black",
+    };
+    match options.format {
+        OutputFormat::Text => {
+            let counts = match options.perspective {
+                Player::White => report.white,
+                Player::Black => report.black,
+            };
+            write!(output, "{} {} {}", counts.moves, counts.mills, counts.takeable)?;
+            if let Some(result) = &report.evaluation {
+                write!(output, " {} {}", result.score, result.millmove)?;
+            }
+            writeln!(output)
+        }
+        OutputFormat::JsonLines => {
+            let counts = |counts: &MoveCounts| format!("{{\"moves\":{},\"mills\":{},\"takeable\":{}}}", counts.moves, counts.mills, counts.takeable);
+            let list = |items: &[String]| format!("[{}]", items.iter().map(|item| json_string(item)).collect::<Vec<String>>().join(","));
+            write!(
+                output,
+                "{{\"line\":{},\"position\":{},\"turn\":\"{}\",\"white\":{},\"black\":{},\"legal_moves\":{},\"mill_moves\":{},\"removable\":{}",
+                report.line, json_string(&report.position), turn, counts(&report.white), counts(&report.black), list(&legal_moves), list(&mill_moves), list(&removable)
+            )?;
+            if let Some(result) = &report.evaluation {
+                write!(output, ",\"score\":{},\"best_move\":{}", result.score, json_string(&result.millmove.to_string()))?;
+            }
+            writeln!(output, "}}")
+        }
+        OutputFormat::Csv => {
+            let (score, best_move) = match &report.evaluation {
+                Some(result) => (result.score.to_string(), result.millmove.to_string()),
+                None => (String::new(), String::new()),
+            };
+            let fields = [
+                report.line.to_string(),
+                csv_field(&report.position),
+                turn.to_string(),
+                report.white.moves.to_string(),
+                report.white.mills.to_string(),
+                report.white.takeable.to_string(),
+                report.black.moves.to_string(),
+                report.black.mills.to_string(),
+                report.black.takeable.to_string(),
+                csv_field(&legal_moves.join(" ")),
+                csv_field(&mill_moves.join(" ")),
+                csv_field(&removable.join(" ")),
+                score,
+                best_move,
+            ];
+            writeln!(output, "{}", fields.join(","))
+        }
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut output = String::from("\"");
+    for character in text.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            character if character.is_control() => output.push_str(&format!("\\u{:04x}", character as u32)),
+            character => output.push(character),
+        }
+    }
+    output.push('"');
+    output
+}
+
+// quotes the field if it contains a separator, a quote or a line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const INPUT: &str = "WWEEEEEE/BBEEEEEE/EEEEEEEE w p 7 7 0\nnot a position\n\n";
+    const MOVES: &str = "d5 e5 e4 e3 d3 c3 c4 c5 f4 f2 d2 b2 b4 b6 g4 g1 d1 a1 a4 a7xd6 a7xf6";
+
+    // the output and the reported lines of analysing the input in the format
+    fn analyze(input: &str, format: OutputFormat) -> (String, Vec<InvalidLine>) {
+        let options = AnalysisOptions { format, ..AnalysisOptions::default() };
+        let mut output = Vec::new();
+        let mut reported = Vec::new();
+        let invalid = analyze_positions(input.as_bytes(), &mut output, &options, |line| reported.push(line.clone())).unwrap();
+        assert_eq!(invalid, reported);
+        (String::from_utf8(output).unwrap(), invalid)
+    }
+
+    #[test]
+    fn rows_of_every_format() {
+        let (text, invalid) = analyze(INPUT, OutputFormat::Text);
+        assert_eq!(text, "20 1 2\n0 0 0\n0 0 0\n");
+        assert_eq!(invalid, vec![InvalidLine { line: 2, error: PositionError::FieldCount { found: 3 } }]);
+        assert_eq!(invalid[0].to_string(), "line 2: expected 6 or 7 fields in position string, found 3");
+
+        let (csv, _) = analyze(INPUT, OutputFormat::Csv);
+        let row = format!("1,WWEEEEEE/BBEEEEEE/EEEEEEEE w p 7 7 0,white,20,1,2,20,1,2,{},a7,d6 f6,,", MOVES);
+        assert_eq!(csv, format!("{}\n{}\n", CSV_HEADER, row));
+
+        let (json, _) = analyze(INPUT, OutputFormat::JsonLines);
+        let moves: Vec<String> = MOVES.split(' ').map(json_string).collect();
+        let object = format!(
+            "{{\"line\":1,\"position\":\"WWEEEEEE/BBEEEEEE/EEEEEEEE w p 7 7 0\",\"turn\":\"white\",\"white\":{{\"moves\":20,\"mills\":1,\"takeable\":2}},\"black\":{{\"moves\":20,\"mills\":1,\"takeable\":2}},\"legal_moves\":[{}],\"mill_moves\":[\"a7\"],\"removable\":[\"d6\",\"f6\"]}}",
+            moves.join(",")
+        );
+        assert_eq!(json, format!("{}\n", object));
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(csv_field("a7 d7"), "a7 d7");
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+        assert_eq!(json_string("say \"hi\"\\\n"), "\"say \\\"hi\\\"\\\\\\u000a\"");
+    }
+
+    #[test]
+    fn exit_code_of_bad_input() {
+        let directory = std::env::temp_dir().join(format!("muehlespiel-analysis-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let (good, bad, output) = (directory.join("good.txt"), directory.join("bad.txt"), directory.join("out.csv"));
+        fs::write(&good, "WWEEEEEE/BBEEEEEE/EEEEEEEE w p 7 7 0\n").unwrap();
+        fs::write(&bad, INPUT).unwrap();
+        let run = |input: &Path, extra: &[&str]| {
+            let mut args: Vec<String> = ["--input", input.to_str().unwrap(), "--output", output.to_str().unwrap(), "--format", "csv"].map(String::from).to_vec();
+            args.extend(extra.iter().map(|arg| arg.to_string()));
+            main(&args)
+        };
+        assert_eq!(run(&good, &[]), ExitCode::SUCCESS);
+        assert_eq!(run(&bad, &[]), ExitCode::FAILURE);
+        //the valid lines are written even if others are not
+        assert_eq!(fs::read_to_string(&output).unwrap().lines().count(), 2);
+        assert_eq!(run(&good, &["--evaluate", "0"]), ExitCode::FAILURE);
+        assert_eq!(run(&good, &["--unknown"]), ExitCode::FAILURE);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
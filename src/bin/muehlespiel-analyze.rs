@@ -0,0 +1,9 @@
+use std::env;
+use std::process::ExitCode;
+
+// usage: muehlespiel-analyze [--input <file>] [--output <file>] [--perspective white|black] [--format text|jsonl|csv] [--evaluate <depth>] [--weights <file>]
+// reports moves, mills and takeable stones of one position per line, stdin and stdout are used unless files are given
+fn main() -> ExitCode {
+  let args: Vec<String> = env::args().skip(1).collect();
+  muehlespiel::analysis::main(&args)
+}
@@ -0,0 +1,4 @@
+// the engine protocol on stdin and stdout, see muehlespiel::protocol for the commands
+fn main() {
+  muehlespiel::protocol::run_engine();
+}
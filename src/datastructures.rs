@@ -1,304 +1,463 @@
+use crate::rules::*;
+use crate::variant::*;
 use std::str::FromStr;
 use std::fmt;
 
 
-#[derive(Clone,Copy,PartialEq)]
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
 pub enum Player {
     Black,
     White,
 }
 
-#[derive(Clone,Copy,PartialEq)]
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
 pub enum Phase {
     Place,
-    Move, 
+    Move,
 }
 
-#[derive(Debug, Clone)]
-pub struct InvalidFormatError;
+// everything that can be wrong with a gameboard or position string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionError {
+    FieldCount { found: usize },
+    BoardLength { expected: usize, found: usize },
+    RingCount { expected: usize, found: usize },
+    RingLength { ring: usize, expected: usize, found: usize },
+    InvalidStone { position: usize, found: char },
+    InvalidSide(String),
+    InvalidPhase(String),
+    InvalidNumber { field: &'static str, found: String },
+    UnknownVariant(String),
+    TooManyStones { player: Player, limit: u8 },
+    PhaseMismatch,
+    NothingToRemove,
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PositionError::FieldCount { found } => write!(f, "expected 6 or 7 fields in position string, found {}", found),
+            PositionError::BoardLength { expected, found } => write!(f, "expected {} fields in gameboard string, found {}", expected, found),
+            PositionError::RingCount { expected, found } => write!(f, "expected {} rings separated by '/', found {}", expected, found),
+            PositionError::RingLength { ring, expected, found } => write!(f, "ring {} must have {} fields, found {}", ring, expected, found),
+            PositionError::InvalidStone { position, found } => write!(f, "unexpected token '{}' at field {} (expected W, B or E)", found, position),
+            PositionError::InvalidSide(found) => write!(f, "side to move must be 'w' or 'b', found '{}'", found),
+            PositionError::InvalidPhase(found) => write!(f, "phase must be 'p' or 'm', found '{}'", found),
+            PositionError::InvalidNumber { field, found } => write!(f, "invalid {}: '{}'", field, found),
+            PositionError::UnknownVariant(found) => write!(f, "unknown variant '{}' (expected three, six, nine or twelve)", found),
+            PositionError::TooManyStones { player, limit } => write!(f, "{} has more than {} stones on board and in hand", decode_player(Some(*player)), limit),
+            PositionError::PhaseMismatch => write!(f, "phase doesn't match the stones in hand"),
+            PositionError::NothingToRemove => write!(f, "a removal is pending, but there's no stone that could be taken"),
+        }
+    }
+}
+
+// everything that can go wrong when changing the stones of a gameboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardError {
+    OutOfBounds(u8),
+    Occupied(u8),
+    PlaceInMovePhase,
+    TooManyStones(Player),
+    NoStoneToRemove(Player),
+    GameOver,
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoardError::OutOfBounds(index) => write!(f, "field {} is not on the board", index),
+            BoardError::Occupied(index) => write!(f, "there's already a stone at field {}", index),
+            BoardError::PlaceInMovePhase => write!(f, "can't place a stone in the move phase"),
+            BoardError::TooManyStones(player) => write!(f, "{} has already placed all stones", decode_player(Some(*player))),
+            BoardError::NoStoneToRemove(player) => write!(f, "{} has no stone on the board that could be removed", decode_player(Some(*player))),
+            BoardError::GameOver => write!(f, "a player has less than 3 stones left, the game is already over"),
+        }
+    }
+}
+
+// every rule a move can break
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    NotYourTurn(Player),
+    OutOfBounds,
+    Occupied(u8),
+    AllStonesPlaced,
+    NoStonesInHand,
+    StillPlacing,
+    NoStoneAt(u8),
+    OpponentStone(u8),
+    Unreachable { origin: u8, destination: u8 },
+    RemovalPending,
+    NothingToRemove,
+    NoMillClosed(u8),
+    NotTakeable(u8),
+    MissingRemoval,
+    Board(BoardError),
+}
 
-impl fmt::Display for InvalidFormatError {
+impl From<BoardError> for MoveError {
+    fn from(err: BoardError) -> Self {
+        MoveError::Board(err)
+    }
+}
+
+impl fmt::Display for MoveError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "invalid format of gameboard string")
+        match self {
+            MoveError::NotYourTurn(player) => write!(f, "it's not the turn of {}", decode_player(Some(*player))),
+            MoveError::OutOfBounds => write!(f, "origin or destination is not a field on the board"),
+            MoveError::Occupied(index) => write!(f, "field {} is occupied", index),
+            MoveError::AllStonesPlaced => write!(f, "all stones have been placed already"),
+            MoveError::NoStonesInHand => write!(f, "the player has no stones left to place"),
+            MoveError::StillPlacing => write!(f, "stones can only be moved once all are placed"),
+            MoveError::NoStoneAt(index) => write!(f, "there's no stone at field {}", index),
+            MoveError::OpponentStone(index) => write!(f, "the stone at field {} belongs to the opponent", index),
+            MoveError::Unreachable { origin, destination } => write!(f, "field {} can't be reached from field {} in one move", destination, origin),
+            MoveError::RemovalPending => write!(f, "a stone has to be taken before moving on"),
+            MoveError::NothingToRemove => write!(f, "there's no stone to be taken right now"),
+            MoveError::NoMillClosed(index) => write!(f, "can't take the stone at field {} without closing a mill", index),
+            MoveError::NotTakeable(index) => write!(f, "the stone at field {} can't be taken", index),
+            MoveError::MissingRemoval => write!(f, "the move closes a mill, but takes no stone"),
+            MoveError::Board(err) => write!(f, "{}", err),
+        }
     }
 }
 
+// the fields are indexed from 1 up to the field count of the variant, field i is stored in bit i-1 of a mask.
+// the layout of the fields, lines and mills comes from the topology of the variant
+
+// returns the mask with only the given field set
+pub const fn field_bit(index: u8) -> u32 {
+    1 << (index - 1)
+}
+
+// random keys for the zobrist hash of a position
+const ZOBRIST_STONES: [[u64; 25]; 2] = [zobrist_keys::<25>(1), zobrist_keys::<25>(2)];
+const ZOBRIST_IN_HAND: [[u64; 13]; 2] = [zobrist_keys::<13>(3), zobrist_keys::<13>(4)];
+const ZOBRIST_PENDING: [u64; 4] = zobrist_keys::<4>(5);
+const ZOBRIST_BLACK_TO_MOVE: u64 = zobrist_keys::<1>(6)[0];
+const ZOBRIST_MOVE_PHASE: u64 = zobrist_keys::<1>(7)[0];
+
+// splitmix64 numbers, the stream picks a different sequence for every table.
+// the streams start far apart, consecutive starts would share most of their keys
+const fn zobrist_keys<const N: usize>(stream: u64) -> [u64; N] {
+    let mut keys = [0u64; N];
+    let mut state = (stream << 32).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut i = 0;
+    while i < N {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+const fn player_index(color: Player) -> usize {
+    match color {
+        Player::Black => 0,
+        Player::White => 1,
+    }
+}
+
+// iterates over the field indexes of all set bits of a mask
+pub fn fields_of_mask(mask: u32) -> impl Iterator<Item = u8> {
+    let mut rest = mask;
+    std::iter::from_fn(move || {
+        if rest == 0 {
+            None
+        } else {
+            let index = rest.trailing_zeros() as u8 + 1;
+            rest &= rest - 1;
+            Some(index)
+        }
+    })
+}
+
 #[derive(Clone)]
 pub struct GameBoard {
-    pub   board: String,
-    pub   gamephase: Phase,
-    pub      white_stones: u8, 
-    pub      black_stones: u8, 
-    pub   total_placed_white_stones: u8,
-    pub   total_placed_black_stones: u8,
-} 
+    white_fields: u32,
+    black_fields: u32,
+    // zobrist hash of the stones on the board, kept up to date by every change of a field
+    stone_hash: u64,
+    pub(crate)   gamephase: Phase,
+    pub(crate)      white_stones: u8,
+    pub(crate)      black_stones: u8,
+    pub(crate)   total_placed_white_stones: u8,
+    pub(crate)   total_placed_black_stones: u8,
+    pub(crate)   turn: Player,
+    pub(crate)   pending_removals: u8,
+    pub(crate)   variant: Variant,
+    pub(crate)   rules: Rules,
+}
+
+impl Default for GameBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GameBoard {
     //constructs new Gameboard
     pub fn new() -> GameBoard {
-        let game_board : GameBoard = GameBoard {
-            board : String::from("EEEEEEEEEEEEEEEEEEEEEEEE"),
+        GameBoard::with_variant(Variant::Nine)
+    }
+
+    //constructs new empty Gameboard of the variant with its usual rules
+    pub fn with_variant(variant: Variant) -> GameBoard {
+        GameBoard::with_rules(variant, Rules::new(variant))
+    }
+
+    //constructs new empty Gameboard of the variant played with the given rules
+    pub fn with_rules(variant: Variant, rules: Rules) -> GameBoard {
+        GameBoard {
+            white_fields: 0,
+            black_fields: 0,
+            stone_hash: 0,
             gamephase : Phase::Place,
             white_stones : 0,
             black_stones : 0,
             total_placed_white_stones: 0,
             total_placed_black_stones: 0,
-        }; 
-        game_board
+            turn: Player::White,
+            pending_removals: 0,
+            variant,
+            rules,
+        }
     }
 
+    pub fn get_turn(&self) -> Player {
+        self.turn
+    }
+    pub fn get_phase(&self) -> Phase {
+        self.gamephase
+    }
+    pub fn get_variant(&self) -> Variant {
+        self.variant
+    }
+    pub fn get_rules(&self) -> Rules {
+        self.rules
+    }
+    // the rules can be changed as long as the stones stay where they are, e.g. for a position read from a string
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+    // stones the player to move still has to take after closing a mill
+    pub fn get_pending_removals(&self) -> u8 {
+        self.pending_removals
+    }
     pub fn get_whitestones(&self)-> u8{
-    self.white_stones.clone()
-   }
+        self.white_stones
+    }
     pub fn get_blackstones(&self) -> u8{
-    self.black_stones.clone()
-   }
+        self.black_stones
+    }
+    pub fn get_stones_of(&self, color: Player) -> u8 {
+        match color {
+            Player::Black => self.black_stones,
+            Player::White => self.white_stones,
+        }
+    }
+    pub fn get_stones_in_hand(&self, color: Player) -> u8 {
+        match color {
+            Player::Black => self.variant.stones() - self.total_placed_black_stones,
+            Player::White => self.variant.stones() - self.total_placed_white_stones,
+        }
+    }
+    // returns the mask of all fields occupied by the color
+    pub fn get_stone_mask(&self, color: Player) -> u32 {
+        match color {
+            Player::Black => self.black_fields,
+            Player::White => self.white_fields,
+        }
+    }
+    // returns the mask of all empty fields
+    pub fn get_free_mask(&self) -> u32 {
+        self.variant.all_fields() & !(self.white_fields | self.black_fields)
+    }
     //returns the color at the specified index
-    pub fn get_player_at(&self, index:u8) -> Option<Player> { 
-        if index < 1 || index > 24 {
-            panic!("Index out of bounds! Must be between 1 and 24!");
-        }
-        let temp_board: &String = &self.board; 
-        let x: char = temp_board.chars().nth((index-1) as usize).unwrap(); 
-        if x == 'B' {
-            return Some(Player::Black)
-        } else if x =='W' {
-            return Some(Player::White)
-        } else if x =='E' {
-            return None 
+    pub fn get_player_at(&self, index:u8) -> Result<Option<Player>, BoardError> {
+        self.check_bounds(index)?;
+        Ok(self.stone_at(index))
+    }
+    fn check_bounds(&self, index: u8) -> Result<(), BoardError> {
+        if (1..=self.variant.field_count()).contains(&index) {
+            Ok(())
         } else {
-            panic!("Illegal Gameboard state: unexpected field token!");
-        }
-   }
-    // true if specified field is empty
-    pub fn is_free_at(&self, index: u8)-> bool { 
-        matches!(self.get_player_at(index), None)
-   }
-   
-   // returns a Vector<u8> of all free fields
-   pub fn get_free_fields(&self)-> Vec<u8> {
-        let mut output_vec : Vec<u8> = Vec::new();
-        for i in 1..=24 {
-            if self.is_free_at(i) {
-                output_vec.push(i);
-            }
+            Err(BoardError::OutOfBounds(index))
         }
-        output_vec
-   }
-
-    // changes the current stone type on the selected field. Only usable if field is empty!  
-    pub fn set_stone_at(&mut self, index: u8, color: Player){
-        if self.is_free_at(index) {
-            self.board.replace_range((index-1)as usize ..index as usize , (decode_player(Some(color))).to_string().as_str());
+    }
+    // the color at an index known to be on the board
+    pub(crate) fn stone_at(&self, index: u8) -> Option<Player> {
+        let bit = field_bit(index);
+        if self.black_fields & bit != 0 {
+            Some(Player::Black)
+        } else if self.white_fields & bit != 0 {
+            Some(Player::White)
         } else {
-            panic!("Invalid action: there's already a stone at field {}", index);
+            None
         }
     }
-    // returns a Vector<u8> of all neighbours 
-    fn get_neighbours(&self, index: u8)-> Vec<u8> {
-        let mut neighbours: Vec<u8> = Vec::new();
-        if index % 2 == 0 {
-            if index % 8 == 0 {
-                neighbours.push(index-1);
-                neighbours.push(index-7);
-            } else {
-                neighbours.push(index-1);
-                neighbours.push(index+1);
-            }
-        } else {
-            let ring = determine_ring(index); 
-            if index % 8 == 1 {
-                neighbours.push(index+7);
-                neighbours.push(index+1);
-            } else {
-                neighbours.push(index-1);
-                neighbours.push(index+1);
-            }
-            if ring == 1 {
-                neighbours.push(index+8);
-            } else if ring ==2 {
-                neighbours.push(index-8);
-                neighbours.push(index+8);
-            } else if ring ==3 {
-                neighbours.push(index-8);
+    // zobrist hash of the whole position: stones, side to move, phase, stones in hand and pending removals
+    pub fn hash(&self) -> u64 {
+        let mut hash = self.stone_hash
+            ^ ZOBRIST_IN_HAND[player_index(Player::White)][self.get_stones_in_hand(Player::White) as usize]
+            ^ ZOBRIST_IN_HAND[player_index(Player::Black)][self.get_stones_in_hand(Player::Black) as usize]
+            ^ ZOBRIST_PENDING[self.pending_removals.min(3) as usize];
+        if self.turn == Player::Black {
+            hash ^= ZOBRIST_BLACK_TO_MOVE;
+        }
+        if self.gamephase == Phase::Move {
+            hash ^= ZOBRIST_MOVE_PHASE;
+        }
+        hash
+    }
+    // true if specified field is on the board and empty
+    pub fn is_free_at(&self, index: u8)-> bool {
+        self.get_player_at(index) == Ok(None)
+    }
+
+    // returns a Vector<u8> of all free fields
+    pub fn get_free_fields(&self)-> Vec<u8> {
+        fields_of_mask(self.get_free_mask()).collect()
+    }
+
+    // changes the current stone type on the selected field. Only usable if field is empty!
+    pub fn set_stone_at(&mut self, index: u8, color: Player) -> Result<(), BoardError> {
+        if self.get_player_at(index)?.is_some() {
+            return Err(BoardError::Occupied(index))
+        }
+        match color {
+            Player::Black => self.black_fields |= field_bit(index),
+            Player::White => self.white_fields |= field_bit(index),
+        }
+        self.stone_hash ^= ZOBRIST_STONES[player_index(color)][index as usize];
+        Ok(())
+    }
+    // the same board with the stones of both colors replaced by the masks, the counters stay the same
+    pub(crate) fn with_stone_masks(&self, white_mask: u32, black_mask: u32) -> GameBoard {
+        let all_fields = self.variant.all_fields();
+        let mut gameboard = GameBoard { white_fields: white_mask & all_fields, black_fields: black_mask & all_fields & !white_mask, stone_hash: 0, ..self.clone() };
+        for color in [Player::White, Player::Black] {
+            for field in fields_of_mask(gameboard.get_stone_mask(color)) {
+                gameboard.stone_hash ^= ZOBRIST_STONES[player_index(color)][field as usize];
             }
-        }    
-        neighbours 
+        }
+        gameboard
+    }
+    // returns a Vector<u8> of all neighbours
+    fn get_neighbours(&self, index: u8)-> Vec<u8> {
+        fields_of_mask(self.variant.topology().neighbours[index as usize]).collect()
     }
     pub fn set_gamephase(&mut self, phase:Phase){
-         self.gamephase = match phase {
-            Phase::Place => {Phase::Place},
-            Phase::Move => {Phase::Move},
-        };
+        self.gamephase = phase;
     }
-    pub fn del_stone_at(&mut self, index: u8){
-        if !self.is_free_at(index){
-            self.board.replace_range((index-1) as usize ..index as usize, (decode_player(None)).to_string().as_str());
+    pub fn del_stone_at(&mut self, index: u8) -> Result<(), BoardError> {
+        if let Some(color) = self.get_player_at(index)? {
+            self.stone_hash ^= ZOBRIST_STONES[player_index(color)][index as usize];
         }
+        let cleared = !field_bit(index);
+        self.white_fields &= cleared;
+        self.black_fields &= cleared;
+        Ok(())
     }
-    pub fn  increment_stone_counter (&mut self, player: Player){
-        let current_phase = self.gamephase; 
-        match current_phase {
-            Phase::Place => {
-                match player {
-                    Player::Black => {
-                    self.black_stones +=1;
-                    self.total_placed_black_stones +=1;
-                    }
-                    Player::White => {
-                        self.white_stones +=1;
-                        self.total_placed_white_stones +=1;
-                    }
-                }
-                if self.total_placed_black_stones > 9 || self.total_placed_white_stones > 9 {
-                    panic!("Illegal State: Stone count can't be more than 9!");
-                }
-            }
-            Phase::Move => {
-                panic!("Illegal State: Can't place stone in move phase!");
-                }
-            }
+    pub fn  increment_stone_counter (&mut self, player: Player) -> Result<(), BoardError> {
+        if self.gamephase == Phase::Move {
+            return Err(BoardError::PlaceInMovePhase)
         }
-    pub fn  decrement_stone_counter (&mut self, player: Player){
-        if (self.total_placed_black_stones==9 && self.black_stones < 3 )||(self.total_placed_white_stones==9 && self.white_stones < 3) {
-            panic!("Illegal State: Game should be over by now!"); 
+        if self.get_stones_in_hand(player) == 0 {
+            return Err(BoardError::TooManyStones(player))
         }
         match player {
-            Player::Black => {self.black_stones -=1;}
-            Player::White => {self.white_stones -=1;} 
+            Player::Black => {
+                self.black_stones +=1;
+                self.total_placed_black_stones +=1;
+            }
+            Player::White => {
+                self.white_stones +=1;
+                self.total_placed_white_stones +=1;
+            }
         }
+        Ok(())
     }
-           
-    //checks two specific fields for a certain color, returns true if they all have the same. 
-    fn is_color_matching (&self, color: Player, tuple: (u8, u8)) -> bool {
-        match self.get_player_at(tuple.0) {
-            None => {return false}
-            _ => {}
+    pub fn  decrement_stone_counter (&mut self, player: Player) -> Result<(), BoardError> {
+        if self.black_stones + self.get_stones_in_hand(Player::Black) < 3 || self.white_stones + self.get_stones_in_hand(Player::White) < 3 {
+            return Err(BoardError::GameOver)
         }
-        match self.get_player_at(tuple.1) {
-            None => {return false}
-            _ => {}
+        if self.get_stones_of(player) == 0 {
+            return Err(BoardError::NoStoneToRemove(player))
         }
-        let color_0 : Player = self.get_player_at(tuple.0).unwrap();
-        let color_1: Player = self.get_player_at(tuple.1).unwrap();
-        match color_0 {
-           c if c == color => {}
-            _ => {return false}
-        }
-        match color_1 {
-           c if c == color => {return true}
-            _ => {return false} 
+        match player {
+            Player::Black => {self.black_stones -=1;}
+            Player::White => {self.white_stones -=1;}
         }
+        Ok(())
     }
-    //returns true if the index is part of a mill 
+
+    //returns true if the index is part of a mill
     pub fn mill_checker (&self, index: u8)->bool {
-        let color: Option<Player> = self.get_player_at(index);
-        let result :bool = false;
-        if color.is_none() {return false}
-        let color: Player = color.unwrap();
-        //case 1: middle field
-        if index % 2 == 1 {
-        let ring: u8 = determine_ring(index);
-            
-            if ring == 1 && self.is_color_matching(color, ((index+8), (index+16))) {
-                return true
-            } else if ring == 2 && self.is_color_matching(color, ((index-8),(index+8))) {
-                return true
-            } else if ring == 3 && self.is_color_matching(color, ((index-8),(index-16))) {
-                return true
-            }
-            if index % 8 ==1 {
-             if  self.is_color_matching(color, ((index+1),(index+7))) {
-                return true }
-            } else if self.is_color_matching(color, ((index+1),(index-1))) {
-                return true 
-            } else {return false}
-        //case 2: edge field     
-        } else {
-            if index % 8 == 0 {
-                if self.is_color_matching(color, ((index-1),(index-2))) || self.is_color_matching(color, ((index-6),(index-7))) {
-                    return true
-                } else {return false }
-            }  else if index % 8 == 2 {
-                if self.is_color_matching(color, ((index-1),(index+6)))|| self.is_color_matching(color, ((index+1),(index+2))) {
-                    return true 
-                } else {return false }
-            } else if self.is_color_matching(color, ((index-1),(index-2))) || self.is_color_matching(color, ((index+1),(index+2))) {
-                return true 
-            } else {return false }
-             
-        }
-        result 
+        match self.get_player_at(index) {
+            Ok(Some(color)) => self.is_mill_with(self.get_stone_mask(color), index),
+            _ => false,
+        }
+    }
+    // true if one of the mills through the index is fully covered by the mask
+    fn is_mill_with(&self, mask: u32, index: u8) -> bool {
+        self.variant.topology().mills_of[index as usize].iter().any(|mill| *mill != 0 && mask & mill == *mill)
     }
     pub fn get_all_stones_of (&self, color: Player)-> Vec<u8> {
-        let mut output: Vec<u8> = Vec::new();
-        let board_string = &self.board;
-        let search = decode_player(Some(color));
-        for (index, character) in board_string.chars().enumerate() {
-            if character == search {
-                output.push((index+1) as u8); 
-            }
-        }
-        output 
+        fields_of_mask(self.get_stone_mask(color)).collect()
+    }
+    // true if the stones of the color may jump to any free field
+    pub fn can_fly(&self, color: Player) -> bool {
+        self.rules.flying && self.get_stones_of(color) + self.get_stones_in_hand(color) == 3
+    }
+    // true if the color can neither place nor move a stone
+    pub fn is_blocked(&self, color: Player) -> bool {
+        let can_place = self.gamephase == Phase::Place && self.get_stones_in_hand(color) > 0 && self.get_free_mask() != 0;
+        let can_move = (self.gamephase == Phase::Move || self.rules.lasker) && self.has_moves_left(color);
+        !can_place && !can_move
+    }
+    // true if all stones are placed and fill every field, only the 24 stones of Twelve Men's Morris can do that.
+    // such a game is a draw, no matter what the rules say about blocked players
+    pub fn is_full(&self) -> bool {
+        self.pending_removals == 0 && self.get_free_mask() == 0
     }
     pub fn has_only_mills (&self, color: Player)-> bool {
-        let result :bool = true; 
-        let instances : Vec<u8> = self.get_all_stones_of(color);
-        for stone in instances {
-            if !self.mill_checker(stone){
-                return false 
-            }
-        }
-        result 
-    }
-     
-     //enumerates all possible moves of one fixed position inside a Vector<MillMove>
-     fn enurmerate_moves(&self, position: u8, result: &mut Vec<MillMove>){
-        let mut _player= self.get_player_at(position);
-        let neighbour_vec = self.get_neighbours(position);
-        let free_field_vec = self.get_free_fields();
-        let free_field_vec_clone = free_field_vec.clone();
-        match _player {
-            None=> {result.clear();}
-            Some(millplayer) => {
-                result.clear();
-                let _player = millplayer; 
-                let stone_amount = match _player {
-                    Player::Black => {self.get_blackstones()}
-                    Player::White => {self.get_whitestones()}
-                }; 
-                if stone_amount == 3 {
-                    for free_field in free_field_vec {
-                        let possible_move: MillMove = MillMove::new(_player, &self, position, free_field);
-                        if possible_move.is_valid(self){
-                            result.push(possible_move);
-                        }
-                    }
-                } else {
-                    for neighbour in neighbour_vec {
-                        if self.is_free_at(neighbour) {
-                            let possible_move: MillMove = MillMove::new(_player, &self, position, neighbour);
-                            if possible_move.is_valid(self){
-                                result.push(possible_move);
-                            }
-                        }
-                    }
+        fields_of_mask(self.get_stone_mask(color)).all(|stone| self.mill_checker(stone))
+    }
+
+    //enumerates all possible moves of one fixed position inside a Vector<MillMove>
+    fn enurmerate_moves(&self, position: u8, result: &mut Vec<MillMove>){
+        result.clear();
+        if let Ok(Some(player)) = self.get_player_at(position) {
+            //with three stones left every free field can be reached
+            let targets: Vec<u8> = if self.can_fly(player) {
+                self.get_free_fields()
+            } else {
+                fields_of_mask(self.variant.topology().neighbours[position as usize] & self.get_free_mask()).collect()
+            };
+            for target in targets {
+                let possible_move: MillMove = MillMove::new(player, self, position, target);
+                if possible_move.validate_any_turn(self).is_ok(){
+                    result.push(possible_move);
                 }
             }
         }
-        
     }
 
-    //returns a Vec<MillMove> of all possible moves of the player. 
-    fn possile_moves_vector(&self, player:Player) -> Vec<MillMove> {
+    //returns a Vec<MillMove> of all possible moves of the player.
+    pub fn possile_moves_vector(&self, player:Player) -> Vec<MillMove> {
         let mut output: Vec<MillMove> = Vec::new();
-        let player_instances: Vec<u8> = self.get_all_stones_of(player);
         let mut temp_moves_vec: Vec<MillMove> = Vec::new();
-            for instance in player_instances {
-                self.enurmerate_moves(instance, &mut temp_moves_vec);
-                output.extend(temp_moves_vec.drain(..));
-                temp_moves_vec.clear();
-            }
-        output 
+        for instance in self.get_all_stones_of(player) {
+            self.enurmerate_moves(instance, &mut temp_moves_vec);
+            output.append(&mut temp_moves_vec);
+        }
+        output
     }
 
     pub fn possible_moves_amount(&self, player: Player)-> u8{
@@ -306,162 +465,356 @@ impl GameBoard {
     }
 
     pub fn possible_mill_amount(&self, player: Player) -> u8 {
-        let all_moves = self.possile_moves_vector(player);
-        let mut amount: u8 = 0;
-        for one_move in all_moves {
-            let temp_board = self.move_simulator(one_move);
-            if temp_board.mill_checker(one_move.destination){
-                amount +=1;
-            }
-        }
-        amount 
+        self.possile_moves_vector(player)
+            .iter()
+            .filter(|one_move| one_move.closes_mill(self))
+            .count() as u8
     }
     //returns amount of possible opponent stones, that could be taken when closing a mill
     pub fn takeable_opponent_amount(&self, player: Player)-> u8 {
-        let mut amount: u8 = 0;
-        if self.possible_mill_amount(player) > 0 {
+        if self.possible_mill_amount(player) == 0 {
+            return 0
+        }
+        self.get_takeable_stones(player).len() as u8
+    }
+    //returns all opponent stones the player may take after closing a mill
+    pub fn get_takeable_stones(&self, player: Player) -> Vec<u8> {
         let opponent = get_other_player(player);
-        if self.has_only_mills(opponent){
-            let stone_amount = match opponent {
-                Player::Black => {self.get_blackstones()}
-                Player::White => {self.get_whitestones()}
-            }; 
-            amount = stone_amount;
+        if self.rules.take_from_mills || self.has_only_mills(opponent){
+            self.get_all_stones_of(opponent)
         } else {
-            let opponent_instances = self.get_all_stones_of(opponent);
-            for opponent_instance in opponent_instances {
-                if !self.mill_checker(opponent_instance){
-                    amount +=1;
+            fields_of_mask(self.get_stone_mask(opponent))
+                .filter(|stone| !self.mill_checker(*stone))
+                .collect()
+        }
+    }
+
+    pub fn has_moves_left(&self, player:Player)->bool {
+        self.possible_moves_amount(player) > 0
+    }
+
+    //returns all complete turns of the player, a mill-closing move once for every stone it can take
+    pub fn possible_turns(&self, player: Player) -> Vec<MillMove> {
+        if self.pending_removals > 0 {
+            return self.get_takeable_stones(player)
+                .into_iter()
+                .map(|stone| MillMove::removal(player, self, stone))
+                .collect()
+        }
+        let moves: Vec<MillMove> = match self.gamephase {
+            Phase::Place => {
+                let mut moves: Vec<MillMove> = Vec::new();
+                if self.get_stones_in_hand(player) > 0 {
+                    moves.extend(fields_of_mask(self.get_free_mask())
+                        .map(|field| MillMove::new(player, self, 0, field))
+                        .filter(|millmove| millmove.validate_any_turn(self).is_ok()));
                 }
+                //with Lasker rules stones may also be moved while placing
+                if self.rules.lasker {
+                    moves.extend(self.possile_moves_vector(player));
+                }
+                moves
+            }
+            Phase::Move => self.possile_moves_vector(player),
+        };
+        let takeable = self.get_takeable_stones(player);
+        let mut turns: Vec<MillMove> = Vec::new();
+        for millmove in moves {
+            if millmove.closes_mill(self) && !takeable.is_empty() {
+                turns.extend(takeable.iter().map(|stone| MillMove { remove: Some(*stone), ..millmove }));
+            } else {
+                turns.push(millmove);
             }
         }
-        }
-        amount 
+        turns
     }
 
-    pub fn has_moves_left(&self, player:Player)->bool {
-        if self.possible_moves_amount(player) > 0 {
-            return true
-        } else {
-            return false 
+    // the number of mills moving a stone of the color from origin (0 when placing) to destination closes
+    fn closed_mills(&self, color: Player, origin: u8, destination: u8) -> usize {
+        let mut mask = self.get_stone_mask(color) | field_bit(destination);
+        if origin != 0 {
+            mask &= !field_bit(origin);
         }
+        self.variant.topology().mills_of[destination as usize].iter().filter(|mill| **mill != 0 && mask & **mill == **mill).count()
+    }
+
+    //simulates a single move on current gameboard an outputs a new, updated board
+    pub fn move_simulator(&self, millmove: MillMove)->Result<GameBoard, MoveError>{
+        millmove.validate(self)?;
+        self.apply_move(millmove)
     }
-        
-     //simulates a single move on current gameboard an outputs a new, updated board
-    pub fn move_simulator(&self, millmove: MillMove)->GameBoard{
-         let mut temp_board = self.clone(); 
-         if millmove.is_valid(&temp_board) {
+
+    // simulates a move of either player, used to look at what the player not to move threatens
+    pub(crate) fn move_simulator_any_turn(&self, millmove: MillMove)->Result<GameBoard, MoveError>{
+        millmove.validate_any_turn(self)?;
+        self.apply_move(millmove)
+    }
+
+    fn apply_move(&self, millmove: MillMove)->Result<GameBoard, MoveError>{
+        let mut temp_board = self.clone();
+        let closed_mills = millmove.closed_mills(self);
+        if millmove.is_removal() {
+            temp_board.pending_removals -= 1;
+        } else {
             match millmove.movetype {
                 Phase::Place => {
-                    temp_board.set_stone_at(millmove.destination, millmove.turn );
-                    temp_board.increment_stone_counter(millmove.turn); 
-                    //mill closed?
-                    if temp_board.mill_checker(millmove.destination) {
-                        
-                    }
-                    //all stones have been placed 
-                    if temp_board.total_placed_black_stones == 9 && temp_board.total_placed_white_stones == 9 {
+                    temp_board.set_stone_at(millmove.destination, millmove.turn )?;
+                    temp_board.increment_stone_counter(millmove.turn)?;
+                    //all stones have been placed
+                    if temp_board.get_stones_in_hand(Player::Black) == 0 && temp_board.get_stones_in_hand(Player::White) == 0 {
                         temp_board.set_gamephase(Phase::Move)
                     }
                 }
                 Phase::Move => {
-                    temp_board.set_stone_at(millmove.destination, millmove.turn);
-                    temp_board.del_stone_at(millmove.origin);
+                    temp_board.del_stone_at(millmove.origin)?;
+                    temp_board.set_stone_at(millmove.destination, millmove.turn)?;
                 }
             }
-        } else {
-            println!("Couldn't apply changes! Move not valid!");
         }
-        temp_board
-    }   
-   
+        //mill closed
+        if let Some(stone) = millmove.remove {
+            temp_board.del_stone_at(stone)?;
+            temp_board.decrement_stone_counter(get_other_player(millmove.turn))?;
+            //the second stone of a double mill is taken by a removal of the same player
+            let opponent = get_other_player(millmove.turn);
+            let opponent_left = temp_board.get_stones_of(opponent) + temp_board.get_stones_in_hand(opponent);
+            if temp_board.rules.double_removal && closed_mills > 1 && opponent_left >= 3 && !temp_board.get_takeable_stones(millmove.turn).is_empty() {
+                temp_board.pending_removals += 1;
+            }
+        }
+        if temp_board.pending_removals == 0 {
+            temp_board.turn = get_other_player(millmove.turn);
+        }
+        Ok(temp_board)
+    }
+
 
 
+    //prints the board with the standard coordinates a1 to g7 at its edges
     pub fn print_gameboard(&self){
-        let a = decode_player(self.get_player_at(24));
-        let b = decode_player(self.get_player_at(17));
-        let c = decode_player(self.get_player_at(18));
-        println!("{}------------{}------------{}", a, b, c);
-        println!("|            |            |");
-
-        let a = decode_player(self.get_player_at(16));
-        let b = decode_player(self.get_player_at(9));
-        let c = decode_player(self.get_player_at(10));
-        println!("|   {}--------{}--------{}   |", a, b, c);
-        println!("|   |        |        |   |");
-
-        let a = decode_player(self.get_player_at(8));
-        let b = decode_player(self.get_player_at(1));
-        let c = decode_player(self.get_player_at(2));
-        println!("|   |   {}----{}----{}   |   |", a, b, c);
-        println!("|   |   |         |   |   |");
-
-        let a = decode_player(self.get_player_at(23));
-        let b = decode_player(self.get_player_at(15));
-        let c = decode_player(self.get_player_at(7));
-        let d = decode_player(self.get_player_at(3));
-        let e = decode_player(self.get_player_at(11));
-        let f = decode_player(self.get_player_at(19));
-        println!("{}---{}---{}         {}---{}---{}", a, b, c, d, e, f);
-        println!("|   |   |         |   |   |");
-
-        let a = decode_player(self.get_player_at(6));
-        let b = decode_player(self.get_player_at(5));
-        let c = decode_player(self.get_player_at(4));
-        println!("|   |   {}----{}----{}   |   |", a, b, c);
-
-        println!("|   |        |        |   |");
-        let a = decode_player(self.get_player_at(14));
-        let b = decode_player(self.get_player_at(13));
-        let c = decode_player(self.get_player_at(12));
-        println!("|   {}--------{}--------{}   |", a, b, c);
-
-        println!("|            |            |");
-        let a = decode_player(self.get_player_at(22));
-        let b = decode_player(self.get_player_at(21));
-        let c = decode_player(self.get_player_at(20));
-        println!("{}------------{}------------{}", a, b, c);
+        if self.variant != Variant::Nine {
+            return self.print_grid()
+        }
+        let a = decode_player(self.stone_at(24));
+        let b = decode_player(self.stone_at(17));
+        let c = decode_player(self.stone_at(18));
+        println!("7 {}------------{}------------{}", a, b, c);
+        println!("  |            |            |");
+
+        let a = decode_player(self.stone_at(16));
+        let b = decode_player(self.stone_at(9));
+        let c = decode_player(self.stone_at(10));
+        println!("6 |   {}--------{}--------{}   |", a, b, c);
+        println!("  |   |        |        |   |");
+
+        let a = decode_player(self.stone_at(8));
+        let b = decode_player(self.stone_at(1));
+        let c = decode_player(self.stone_at(2));
+        println!("5 |   |   {}----{}----{}   |   |", a, b, c);
+        println!("  |   |   |         |   |   |");
+
+        let a = decode_player(self.stone_at(23));
+        let b = decode_player(self.stone_at(15));
+        let c = decode_player(self.stone_at(7));
+        let d = decode_player(self.stone_at(3));
+        let e = decode_player(self.stone_at(11));
+        let f = decode_player(self.stone_at(19));
+        println!("4 {}---{}---{}         {}---{}---{}", a, b, c, d, e, f);
+        println!("  |   |   |         |   |   |");
+
+        let a = decode_player(self.stone_at(6));
+        let b = decode_player(self.stone_at(5));
+        let c = decode_player(self.stone_at(4));
+        println!("3 |   |   {}----{}----{}   |   |", a, b, c);
+
+        println!("  |   |        |        |   |");
+        let a = decode_player(self.stone_at(14));
+        let b = decode_player(self.stone_at(13));
+        let c = decode_player(self.stone_at(12));
+        println!("2 |   {}--------{}--------{}   |", a, b, c);
+
+        println!("  |            |            |");
+        let a = decode_player(self.stone_at(22));
+        let b = decode_player(self.stone_at(21));
+        let c = decode_player(self.stone_at(20));
+        println!("1 {}------------{}------------{}", a, b, c);
+        println!("  a   b   c    d    e   f   g");
     }
 }
 
-impl FromStr for GameBoard {
-    type Err = InvalidFormatError;  
+// the string lists the outer ring first, the inner ring last and the centre of the Three Men's Morris board after it
+fn string_position_to_index(variant: Variant, position: usize) -> u8 {
+    let rings = variant.topology().rings as usize;
+    if position >= rings * 8 {
+        return position as u8 + 1
+    }
+    let ring = (rings - position / 8) as u8;
+    ring_field(ring, (position % 8) as u8)
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() == 24 {
-        let mut white: u8 = 0;
-        let mut black: u8 = 0;
-        let boardstring = String::from(format!("{}{}{}", &s[16..24], &s[8..16], &s[0..8]));
-        let gameboard: GameBoard; 
-        for character in &mut s.chars() {
-            if character == 'B' {
-                black +=1;
-            } else if character == 'W' {
-                white +=1;
-            } else if character == 'E' {
-            } else {
-               return Err(InvalidFormatError)
+// the number of fields of every '/' separated part of a position string
+fn ring_lengths(variant: Variant) -> Vec<usize> {
+    let topology = variant.topology();
+    let mut lengths = vec![8; topology.rings as usize];
+    if topology.field_count as usize > lengths.len() * 8 {
+        lengths.push(1);
+    }
+    lengths
+}
+
+impl GameBoard {
+    // a move phase position with all stones placed
+    pub fn from_masks(variant: Variant, white_mask: u32, black_mask: u32, turn: Player) -> GameBoard {
+        let mut gameboard = GameBoard::with_variant(variant).with_stone_masks(white_mask, black_mask);
+        gameboard.white_stones = white_mask.count_ones() as u8;
+        gameboard.black_stones = black_mask.count_ones() as u8;
+        gameboard.total_placed_white_stones = variant.stones();
+        gameboard.total_placed_black_stones = variant.stones();
+        gameboard.gamephase = Phase::Move;
+        gameboard.turn = turn;
+        gameboard
+    }
+
+    // parses a position string "<outer>/<middle>/<inner> <w|b> <p|m> <white in hand> <black in hand> <pending removals> [variant]",
+    // the variant is left out for Nine Men's Morris
+    pub fn from_position(s: &str) -> Result<GameBoard, PositionError> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.len() != 6 && fields.len() != 7 {
+            return Err(PositionError::FieldCount { found: fields.len() })
+        }
+        let variant = match fields.get(6) {
+            Some(name) => Variant::from_name(name).ok_or_else(|| PositionError::UnknownVariant(name.to_string()))?,
+            None => Variant::Nine,
+        };
+        let rings: Vec<&str> = fields[0].split('/').collect();
+        let lengths = ring_lengths(variant);
+        if rings.len() != lengths.len() {
+            return Err(PositionError::RingCount { expected: lengths.len(), found: rings.len() })
+        }
+        for (ring, (content, expected)) in rings.iter().zip(lengths).enumerate() {
+            let found = content.chars().count();
+            if found != expected {
+                return Err(PositionError::RingLength { ring: ring + 1, expected, found })
             }
         }
-         gameboard = GameBoard{
-            board: boardstring,
-            gamephase: Phase::Move,
-            white_stones: white,
-            black_stones: black,
-            total_placed_black_stones: 9,
-            total_placed_white_stones: 9,
+        let mut gameboard = parse_fields(&rings.concat(), variant)?;
+        gameboard.turn = match fields[1] {
+            "w" => Player::White,
+            "b" => Player::Black,
+            other => return Err(PositionError::InvalidSide(other.to_string())),
         };
-            return Ok(gameboard)
-        } else {
-           return Err(InvalidFormatError)
+        let phase = match fields[2] {
+            "p" => Phase::Place,
+            "m" => Phase::Move,
+            other => return Err(PositionError::InvalidPhase(other.to_string())),
+        };
+        let limit = variant.stones();
+        let white_in_hand = parse_number(fields[3], "white stones in hand", limit)?;
+        let black_in_hand = parse_number(fields[4], "black stones in hand", limit)?;
+        gameboard.pending_removals = parse_number(fields[5], "pending removals", limit)?;
+        if white_in_hand + gameboard.white_stones > limit {
+            return Err(PositionError::TooManyStones { player: Player::White, limit })
+        }
+        if black_in_hand + gameboard.black_stones > limit {
+            return Err(PositionError::TooManyStones { player: Player::Black, limit })
+        }
+        gameboard.total_placed_white_stones = limit - white_in_hand;
+        gameboard.total_placed_black_stones = limit - black_in_hand;
+        let all_placed = white_in_hand == 0 && black_in_hand == 0;
+        if all_placed != (phase == Phase::Move) {
+            return Err(PositionError::PhaseMismatch)
+        }
+        gameboard.gamephase = phase;
+        if gameboard.pending_removals > 0 && gameboard.get_takeable_stones(gameboard.turn).is_empty() {
+            return Err(PositionError::NothingToRemove)
+        }
+        Ok(gameboard)
+    }
+
+    // writes the position string read by from_position
+    pub fn to_position(&self) -> String {
+        let stones: Vec<char> = self.to_string().chars().collect();
+        let mut parts: Vec<String> = Vec::new();
+        let mut rest = stones.as_slice();
+        for length in ring_lengths(self.variant) {
+            let (part, next) = rest.split_at(length);
+            parts.push(part.iter().collect());
+            rest = next;
         }
-        
+        let side = match self.turn {
+            Player::White => 'w',
+            Player::Black => 'b',
+        };
+        let phase = match self.gamephase {
+            Phase::Place => 'p',
+            Phase::Move => 'm',
+        };
+        let mut position = format!("{} {} {} {} {} {}", parts.join("/"), side, phase,
+            self.get_stones_in_hand(Player::White), self.get_stones_in_hand(Player::Black), self.pending_removals);
+        if self.variant != Variant::Nine {
+            position = format!("{} {}", position, self.variant.short_name());
+        }
+        position
+    }
+}
+
+// reads the stone tokens of all fields of the variant, outer ring first
+fn parse_fields(s: &str, variant: Variant) -> Result<GameBoard, PositionError> {
+    let found = s.chars().count();
+    let expected = variant.field_count() as usize;
+    if found != expected {
+        return Err(PositionError::BoardLength { expected, found })
+    }
+    let (mut white_mask, mut black_mask) = (0, 0);
+    for (position, character) in s.chars().enumerate() {
+        let index = string_position_to_index(variant, position);
+        match character {
+            'B' => black_mask |= field_bit(index),
+            'W' => white_mask |= field_bit(index),
+            'E' => {}
+            _ => return Err(PositionError::InvalidStone { position: position + 1, found: character }),
+        }
+    }
+    let mut gameboard = GameBoard::with_variant(variant).with_stone_masks(white_mask, black_mask);
+    gameboard.white_stones = white_mask.count_ones() as u8;
+    gameboard.black_stones = black_mask.count_ones() as u8;
+    Ok(gameboard)
+}
+
+fn parse_number(s: &str, field: &'static str, limit: u8) -> Result<u8, PositionError> {
+    match s.parse::<u8>() {
+        Ok(number) if number <= limit => Ok(number),
+        _ => Err(PositionError::InvalidNumber { field, found: s.to_string() }),
+    }
+}
+
+// accepts a full position string or the plain field string of a move phase board.
+// the plain string is read as Nine Men's Morris board, Six and Three Men's Morris boards are told apart by their length
+impl FromStr for GameBoard {
+    type Err = PositionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.contains(char::is_whitespace) {
+            return GameBoard::from_position(s)
+        }
+        let found = s.chars().count();
+        let variant = [Variant::Three, Variant::Six].into_iter().find(|variant| variant.field_count() as usize == found).unwrap_or(Variant::Nine);
+        let mut gameboard = parse_fields(s, variant)?;
+        gameboard.gamephase = Phase::Move;
+        gameboard.total_placed_black_stones = variant.stones();
+        gameboard.total_placed_white_stones = variant.stones();
+        Ok(gameboard)
     }
 }
 
-impl ToString for GameBoard {
-    fn to_string(&self) -> String {
-        String::from(format!("{}{}{}", &self.board[16..24], &self.board[8..16], &self.board[0..8] ))
+impl fmt::Display for GameBoard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for position in 0..self.variant.field_count() as usize {
+            let index = string_position_to_index(self.variant, position);
+            write!(f, "{}", decode_player(self.stone_at(index)))?;
+        }
+        Ok(())
     }
 }
 
@@ -471,80 +824,129 @@ pub struct MillMove {
    pub turn: Player,
    pub origin: u8,
    pub destination: u8,
+   pub remove: Option<u8>,
+   pub variant: Variant,
 }
 impl MillMove {
     pub fn new(current_turn: Player, game_board: &GameBoard, origin: u8, destination: u8)-> MillMove{
-        let millmove: MillMove = MillMove{
-            movetype : game_board.gamephase.clone(),
+        //with Lasker rules a stone on the board may be moved while placing
+        let movetype = if game_board.rules.lasker && origin != 0 { Phase::Move } else { game_board.gamephase };
+        MillMove{
+            movetype,
             turn: current_turn,
-            origin: origin,
-            destination: destination,
-        }; 
-        millmove 
+            origin,
+            destination,
+            remove: None,
+            variant: game_board.variant,
+        }
+    }
+    // a move that only takes a stone, used while a removal is pending
+    pub fn removal(current_turn: Player, game_board: &GameBoard, stone: u8)-> MillMove{
+        MillMove { remove: Some(stone), ..MillMove::new(current_turn, game_board, 0, 0) }
+    }
+    pub fn is_removal(&self) -> bool {
+        self.destination == 0 && self.remove.is_some()
+    }
+    //checks the move and, if set, the stone it takes
+    pub fn validate (&self, gameboard: &GameBoard)->Result<(), MoveError> {
+        if self.turn != gameboard.turn {
+            return Err(MoveError::NotYourTurn(self.turn))
+        }
+        self.validate_any_turn(gameboard)
+    }
+    // validate without the side to move, the move generation also looks at the moves of the player not to move
+    fn validate_any_turn (&self, gameboard: &GameBoard)->Result<(), MoveError> {
+        if gameboard.pending_removals > 0 || self.is_removal() {
+            return self.validate_removal(gameboard)
+        }
+        self.validate_step(gameboard)?;
+        match self.remove {
+            None => Ok(()),
+            Some(stone) if !self.closes_mill(gameboard) => Err(MoveError::NoMillClosed(stone)),
+            Some(stone) if !gameboard.get_takeable_stones(self.turn).contains(&stone) => Err(MoveError::NotTakeable(stone)),
+            Some(_) => Ok(()),
+        }
     }
     pub fn is_valid (&self, gameboard: &GameBoard)->bool {
-        if self.destination < 1 || self.destination > 24 || self.origin < 1 || self.origin > 24 {
-            println!("OutOfBoundsError!: Origin or destination is not a field on the board!"); 
-            return false    
-        }
-        if gameboard.total_placed_black_stones >9 {
-            panic!("Invalid State: too many black stones have been placed! This should not be possible!");
-        }else if gameboard.total_placed_white_stones > 9 {
-            panic!("Invalid State: too many white stines have been placed! This should not be possible!");
-        }
-        let _valid: bool = false;
-        let turn = self.turn;
-        let stone_amount = match turn{
-            Player::Black => {gameboard.get_blackstones()}
-            Player::White => {gameboard.get_whitestones()}
+        self.validate(gameboard).is_ok()
+    }
+    // like validate, but the move also has to take a stone exactly when it has to
+    pub fn validate_complete (&self, gameboard: &GameBoard)->Result<(), MoveError> {
+        self.validate(gameboard)?;
+        if !self.is_removal() && self.remove.is_none() && self.needs_removal(gameboard) {
+            return Err(MoveError::MissingRemoval)
+        }
+        Ok(())
+    }
+    // true if the move closes a mill and there is an opponent stone to take
+    pub fn needs_removal (&self, gameboard: &GameBoard)->bool {
+        self.closes_mill(gameboard) && !gameboard.get_takeable_stones(self.turn).is_empty()
+    }
+    fn validate_removal (&self, gameboard: &GameBoard)->Result<(), MoveError> {
+        match self.remove {
+            _ if !self.is_removal() => Err(MoveError::RemovalPending),
+            _ if gameboard.pending_removals == 0 => Err(MoveError::NothingToRemove),
+            Some(stone) if !gameboard.get_takeable_stones(self.turn).contains(&stone) => Err(MoveError::NotTakeable(stone)),
+            _ => Ok(()),
+        }
+    }
+    pub fn closes_mill (&self, gameboard: &GameBoard)->bool {
+        self.closed_mills(gameboard) > 0
+    }
+    // the number of mills closed by the move, two if a stone completes two lines at once
+    pub fn closed_mills (&self, gameboard: &GameBoard)->usize {
+        if self.is_removal() {
+            return 0
+        }
+        let origin = match self.movetype {
+            Phase::Place => 0,
+            Phase::Move => self.origin,
         };
+        gameboard.closed_mills(self.turn, origin, self.destination)
+    }
+    fn validate_step (&self, gameboard: &GameBoard)->Result<(), MoveError> {
+        //placements come from the hand, so their origin is not checked
+        let field_count = gameboard.variant.field_count();
+        let origin_on_board = self.movetype == Phase::Place || (1..=field_count).contains(&self.origin);
+        if !(1..=field_count).contains(&self.destination) || !origin_on_board {
+            return Err(MoveError::OutOfBounds)
+        }
+        if gameboard.total_placed_black_stones > gameboard.variant.stones() {
+            return Err(BoardError::TooManyStones(Player::Black).into())
+        } else if gameboard.total_placed_white_stones > gameboard.variant.stones() {
+            return Err(BoardError::TooManyStones(Player::White).into())
+        }
         match &self.movetype {
             Phase::Place => {
-                if gameboard.is_free_at(self.destination){
-                    if gameboard.total_placed_black_stones + gameboard.total_placed_white_stones < 18 {
-                        if stone_amount < 10 {
-                            return true 
-                        } else { println!("InvalidStateError: Already placed all possible stones for this player!");
-                                    return false 
-                                }
-                    } else {
-                        println!("InvalidStateError: Already placed all possible stones! Phase should be Move by now!");
-                        return false 
-                    }
+                if !gameboard.is_free_at(self.destination){
+                    Err(MoveError::Occupied(self.destination))
+                } else if gameboard.total_placed_black_stones + gameboard.total_placed_white_stones >= 2 * gameboard.variant.stones() {
+                    Err(MoveError::AllStonesPlaced)
+                } else if gameboard.get_stones_in_hand(self.turn) == 0 {
+                    Err(MoveError::NoStonesInHand)
                 } else {
-                    println!("InvalidActionError: Can't place stone on occupied field!");
-                    return false 
+                    Ok(())
                 }
             }
             Phase::Move => {
+                //without Lasker rules no stone may be moved before all are placed
+                if gameboard.gamephase != Phase::Move && !gameboard.rules.lasker {
+                    return Err(MoveError::StillPlacing)
+                }
                 //checks if player wants to move his own color
-                let _opponent: Player = get_other_player(self.turn);
-                match gameboard.get_player_at(self.origin){
-                    None => {
-                        println!("InvalidActionError: There's no stone at the selected field!");
-                        return false}
-                    Some(player) if player == _opponent => {
-                        println!("Invalid Action! Can't move stone of the opponent!");
-                        return false }    
+                let opponent: Player = get_other_player(self.turn);
+                match gameboard.get_player_at(self.origin)? {
+                    None => return Err(MoveError::NoStoneAt(self.origin)),
+                    Some(player) if player == opponent => return Err(MoveError::OpponentStone(self.origin)),
                     _ => {}
                 }
-                let _origin_color = gameboard.get_player_at(self.origin).unwrap();
-                    if stone_amount == 3 {
-                        if gameboard.is_free_at(self.destination){
-                            return true 
-                        } else {
-                            println!("Invalid Action! Can't jump to occupied field!");
-                            return false 
-                        }
-                    } else {
-                        let neighbours: Vec<u8> = gameboard.get_neighbours(self.origin);
-                        if neighbours.contains(&self.destination) && gameboard.is_free_at(self.destination){
-                            return true 
-                        } else {
-                            println!("InvalidActionError: Either the destination can't be reached in 1 move or the destination is occupied!");
-                            return false 
-                        }
-                    }
+                if !gameboard.is_free_at(self.destination) {
+                    Err(MoveError::Occupied(self.destination))
+                } else if gameboard.can_fly(self.turn) || gameboard.get_neighbours(self.origin).contains(&self.destination) {
+                    Ok(())
+                } else {
+                    Err(MoveError::Unreachable { origin: self.origin, destination: self.destination })
+                }
             }
         }
     }
@@ -553,44 +955,139 @@ impl MillMove {
 
 // produces a char based on the color of the player
 pub fn decode_player (color: Option<Player>)-> char{
-    let output = match color {
-        Some(Player::Black) => { 'B' },
-        Some(Player::White) => {'W'},
-        None => {'E'}
-    };
-    output 
+    match color {
+        Some(Player::Black) => 'B',
+        Some(Player::White) => 'W',
+        None => 'E',
+    }
 }
 
 pub fn decode_phase (phase: Phase)->String {
-    let output: String = match phase {
-        Phase::Place => {String::from("Place")},
-        Phase::Move => {String::from("Move")},
-    };
-    output
+    match phase {
+        Phase::Place => String::from("Place"),
+        Phase::Move => String::from("Move"),
+    }
 }
 
-//determines the ring which the input index lays upon
- fn determine_ring (index:u8)-> u8 {
-    let mut clone: i8 = index.clone() as i8;
-    let mut output : u8 =0;
-    while clone > 0 {
-        for _i in 0..8 {
-            clone -=1; 
-        }
-        output+=1; 
+pub fn get_other_player (color: Player)->Player {
+    match color {
+        Player::Black => Player::White,
+        Player::White => Player::Black,
     }
-    output
 }
 
- pub fn get_other_player (color: Player)->Player {
-    let output: Player = match color {
-        Player::Black => {Player::White },
-        Player::White => {Player::Black },
-    };
-    output
-    
- }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_round_trip() {
+        let positions = [
+            "EEEEEEEE/EEEEEEEE/EEEEEEEE w p 9 9 0",
+            "WEEEEEEE/EEEEEEEE/EEEEEEEE b p 8 9 0",
+            "WWEEEEEE/BBEEEEEE/EEWEEEEB w p 5 6 0",
+            "WWWEEEEE/BBBEEEEE/EEEEEEEE w m 0 0 1",
+            "WWWBEEEE/BBEEEEEE/EWEEEEEE b m 0 0 0",
+            "WEEEEEEE/BEEEEEEE b p 5 5 0 six",
+            "WWWEEEEE/BBEEEEEE w m 0 0 1 six",
+            "WEEEEEEE/B w p 2 2 0 three",
+            "WWEEEEEE/BBEEEEEE/EEEEEEEE w p 10 10 0 twelve",
+        ];
+        for position in positions {
+            let gameboard = GameBoard::from_position(position).unwrap();
+            assert_eq!(gameboard.to_position(), position);
+            let again = GameBoard::from_position(&gameboard.to_position()).unwrap();
+            assert_eq!(again.hash(), gameboard.hash());
+            assert_eq!(again.get_variant(), gameboard.get_variant());
+            assert_eq!(again.get_pending_removals(), gameboard.get_pending_removals());
+        }
+    }
+
+    #[test]
+    fn invalid_positions() {
+        let cases = [
+            ("EEEEEEEE/EEEEEEEE/EEEEEEEE w p 9 9", PositionError::FieldCount { found: 5 }),
+            ("EEEEEEEE/EEEEEEEE w p 9 9 0", PositionError::RingCount { expected: 3, found: 2 }),
+            ("EEEEEEEE/EEEEEEE/EEEEEEEEE w p 9 9 0", PositionError::RingLength { ring: 2, expected: 8, found: 7 }),
+            ("EEEEEEEE/EEEEXEEE/EEEEEEEE w p 9 9 0", PositionError::InvalidStone { position: 13, found: 'X' }),
+            ("EEEEEEEE/EEEEEEEE/EEEEEEEE x p 9 9 0", PositionError::InvalidSide(String::from("x"))),
+            ("EEEEEEEE/EEEEEEEE/EEEEEEEE w q 9 9 0", PositionError::InvalidPhase(String::from("q"))),
+            ("EEEEEEEE/EEEEEEEE/EEEEEEEE w p 9 ten 0", PositionError::InvalidNumber { field: "black stones in hand", found: String::from("ten") }),
+            ("EEEEEEEE/EEEEEEEE w p 6 6 0 seven", PositionError::UnknownVariant(String::from("seven"))),
+            ("WEEEEEEE/EEEEEEEE/EEEEEEEE b p 9 9 0", PositionError::TooManyStones { player: Player::White, limit: 9 }),
+            ("WEEEEEEE/EEEEEEEE/EEEEEEEE w p 0 0 0", PositionError::PhaseMismatch),
+            ("WWWEEEEE/EEEEEEEE/EEEEEEEE w m 0 0 1", PositionError::NothingToRemove),
+            ("EEEE", PositionError::BoardLength { expected: 24, found: 4 }),
+        ];
+        for (position, error) in cases {
+            assert_eq!(position.parse::<GameBoard>().err(), Some(error), "{}", position);
+        }
+    }
+
+    #[test]
+    fn board_errors() {
+        let mut gameboard = GameBoard::new();
+        assert_eq!(gameboard.get_player_at(0), Err(BoardError::OutOfBounds(0)));
+        assert_eq!(gameboard.get_player_at(25), Err(BoardError::OutOfBounds(25)));
+        assert_eq!(gameboard.set_stone_at(5, Player::White), Ok(()));
+        assert_eq!(gameboard.set_stone_at(5, Player::Black), Err(BoardError::Occupied(5)));
+        assert_eq!(gameboard.del_stone_at(30), Err(BoardError::OutOfBounds(30)));
+        //nothing of black is on the board yet
+        assert_eq!(gameboard.decrement_stone_counter(Player::Black), Err(BoardError::NoStoneToRemove(Player::Black)));
+        assert_eq!(gameboard.get_blackstones(), 0);
 
+        let mut moving = GameBoard::from_masks(Variant::Nine, 0b111, 0b11 << 8, Player::White);
+        assert_eq!(moving.increment_stone_counter(Player::White), Err(BoardError::PlaceInMovePhase));
+        assert_eq!(moving.decrement_stone_counter(Player::White), Err(BoardError::GameOver));
+
+        let mut placed = GameBoard::from_position("WWWWWWWW/WEEEEEEE/BEEEEEEE b p 0 8 0").unwrap();
+        assert_eq!(placed.increment_stone_counter(Player::White), Err(BoardError::TooManyStones(Player::White)));
+    }
 
+    #[test]
+    fn move_errors() {
+        let empty = GameBoard::new();
+        let error = |gameboard: &GameBoard, millmove: MillMove| millmove.validate(gameboard).err();
+        assert_eq!(error(&empty, MillMove::new(Player::Black, &empty, 0, 5)), Some(MoveError::NotYourTurn(Player::Black)));
+        assert_eq!(MillMove::new(Player::Black, &empty, 0, 5).validate_complete(&empty), Err(MoveError::NotYourTurn(Player::Black)));
+        assert_eq!(error(&empty, MillMove::new(Player::White, &empty, 0, 25)), Some(MoveError::OutOfBounds));
+        assert_eq!(error(&empty, MillMove::removal(Player::White, &empty, 5)), Some(MoveError::NothingToRemove));
 
+        let placing = GameBoard::from_position("WWEEEEEE/BBEEEEEE/EEEEEEEE w p 7 7 0").unwrap();
+        let field = |name: &str| crate::notation::parse_field(name, Variant::Nine).unwrap();
+        let occupied = MillMove::new(Player::White, &placing, 0, field("d7"));
+        assert_eq!(error(&placing, occupied), Some(MoveError::Occupied(field("d7"))));
+        let no_mill = MillMove { remove: Some(field("d6")), ..MillMove::new(Player::White, &placing, 0, field("a1")) };
+        assert_eq!(error(&placing, no_mill), Some(MoveError::NoMillClosed(field("d6"))));
+        let closing = MillMove::new(Player::White, &placing, 0, field("a7"));
+        assert!(closing.validate(&placing).is_ok());
+        assert_eq!(closing.validate_complete(&placing), Err(MoveError::MissingRemoval));
+        let own_stone = MillMove { remove: Some(field("d7")), ..closing };
+        assert_eq!(error(&placing, own_stone), Some(MoveError::NotTakeable(field("d7"))));
+        assert!(MillMove { remove: Some(field("d6")), ..closing }.validate_complete(&placing).is_ok());
 
+        //four white stones can't fly
+        let moving = GameBoard::from_position("WWWWEEEE/BBBBEEEE/EEEEEEEE w m 0 0 0").unwrap();
+        let step = |origin: &str, destination: &str| MillMove::new(Player::White, &moving, field(origin), field(destination));
+        assert!(step("d7", "a7").validate(&moving).is_ok());
+        assert_eq!(error(&moving, step("a1", "a4")), Some(MoveError::NoStoneAt(field("a1"))));
+        assert_eq!(error(&moving, step("d6", "d5")), Some(MoveError::OpponentStone(field("d6"))));
+        assert_eq!(error(&moving, step("d7", "a1")), Some(MoveError::Unreachable { origin: field("d7"), destination: field("a1") }));
+        assert_eq!(error(&moving, step("d7", "g7")), Some(MoveError::Occupied(field("g7"))));
+        let placement = MillMove { movetype: Phase::Place, ..MillMove::new(Player::White, &moving, 0, field("a1")) };
+        assert_eq!(error(&moving, placement), Some(MoveError::AllStonesPlaced));
+        let empty_hand = GameBoard::from_position("WWWWWWWW/WEEEEEEE/BBEEEEEE w p 0 7 0").unwrap();
+        assert_eq!(error(&empty_hand, MillMove::new(Player::White, &empty_hand, 0, field("d3"))), Some(MoveError::NoStonesInHand));
+        //a slide while placing needs Lasker rules
+        let slide = MillMove { movetype: Phase::Move, ..MillMove::new(Player::White, &placing, field("g7"), field("g4")) };
+        assert_eq!(error(&placing, slide), Some(MoveError::StillPlacing));
+        let mut lasker = placing.clone();
+        lasker.rules.lasker = true;
+        assert!(MillMove::new(Player::White, &lasker, field("g7"), field("g4")).validate(&lasker).is_ok());
+
+        let removing = GameBoard::from_position("WWWWEEEE/BBBBEEEE/EEEEEEEE w m 0 0 1").unwrap();
+        assert_eq!(error(&removing, step("d7", "a7")), Some(MoveError::RemovalPending));
+        assert_eq!(error(&removing, MillMove::removal(Player::White, &removing, field("f4"))), Some(MoveError::NotTakeable(field("f4"))));
+        assert!(MillMove::removal(Player::White, &removing, field("d6")).validate(&removing).is_ok());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::search::*;
+use std::fmt;
+use std::time::Duration;
+
+// strength of a computer opponent: the lower levels search less deep and now and then play a random move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Expert,
+    // searches as deep as its time allows and looks up solved endgames if the tables are loaded
+    Perfect,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [Difficulty::Beginner, Difficulty::Intermediate, Difficulty::Expert, Difficulty::Perfect];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "beginner",
+            Difficulty::Intermediate => "intermediate",
+            Difficulty::Expert => "expert",
+            Difficulty::Perfect => "perfect",
+        }
+    }
+
+    // accepts the name or the number of the level, 1 is beginner
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        let name = name.trim();
+        Difficulty::ALL
+            .into_iter()
+            .enumerate()
+            .find(|(number, difficulty)| name.eq_ignore_ascii_case(difficulty.name()) || name == (number + 1).to_string())
+            .map(|(_, difficulty)| difficulty)
+    }
+
+    // the limits of the search on this level with the weights of the base limits, only perfect play keeps their endgame tables
+    pub fn limits(self, base: &SearchLimits) -> SearchLimits {
+        let (depth, time) = match self {
+            Difficulty::Beginner => (1, None),
+            Difficulty::Intermediate => (3, None),
+            Difficulty::Expert => (6, Some(Duration::from_secs(2))),
+            Difficulty::Perfect => (MAX_DEPTH, Some(Duration::from_secs(5))),
+        };
+        let database = if self == Difficulty::Perfect { base.database.clone() } else { None };
+        SearchLimits { depth: Some(depth), time, nodes: None, database, ..base.clone() }
+    }
+
+    // the chance in percent to play a random move instead of the one found by the search
+    pub fn mistake_percent(self) -> usize {
+        match self {
+            Difficulty::Beginner => 35,
+            Difficulty::Intermediate => 10,
+            Difficulty::Expert | Difficulty::Perfect => 0,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
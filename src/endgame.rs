@@ -0,0 +1,481 @@
+use crate::datastructures::*;
+use crate::rules::*;
+use crate::symmetry::*;
+use crate::variant::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// the tables are built for Nine Men's Morris only
+const VARIANT: Variant = Variant::Nine;
+// a player with less stones has lost, so the database starts with three stones on each side
+pub const MIN_STONES: u8 = 3;
+// longest distance to the result that fits into one byte per position
+const MAX_PLIES: u8 = 254;
+
+// binomial coefficients C(n, k) for n, k <= 24, used to number the stone combinations
+const BINOMIALS: [[usize; 25]; 25] = build_binomials();
+
+const fn build_binomials() -> [[usize; 25]; 25] {
+    let mut table = [[0usize; 25]; 25];
+    let mut n = 0;
+    while n < 25 {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            table[n][k] = table[n - 1][k - 1] + if k < n { table[n - 1][k] } else { 0 };
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+// result of a position for the player to move, with the plies until the game is decided
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EndgameValue {
+    Win(u8),
+    Loss(u8),
+    Draw,
+}
+
+impl EndgameValue {
+    // every position is stored as one byte: 0 is a draw (or not yet known while building),
+    // otherwise the plies to the result plus one, an odd number of plies is a win for the player to move
+    fn decode(value: u8) -> EndgameValue {
+        match value {
+            0 => EndgameValue::Draw,
+            _ if (value - 1) % 2 == 1 => EndgameValue::Win(value - 1),
+            _ => EndgameValue::Loss(value - 1),
+        }
+    }
+}
+
+// summary of a solved group of positions, as passed to the progress report while building
+pub struct SubspaceSummary {
+    pub own_stones: u8,
+    pub other_stones: u8,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    pub longest: u8,
+}
+
+// all move phase positions with the same number of stones of the player to move and of the opponent.
+// the stones of the player to move are reduced to one representative of the 16 symmetric ones,
+// the stones of the opponent are numbered among the remaining free fields
+struct Subspace {
+    own_sets: Vec<u32>,
+    other_stones: u8,
+    values: Vec<u8>,
+}
+
+impl Subspace {
+    fn new(own_stones: u8, other_stones: u8) -> Subspace {
+        let own_sets = canonical_sets(own_stones);
+        let size = own_sets.len() * BINOMIALS[(VARIANT.field_count() - own_stones) as usize][other_stones as usize];
+        Subspace { own_sets, other_stones, values: vec![0; size] }
+    }
+
+    fn other_combinations(&self) -> usize {
+        self.values.len() / self.own_sets.len()
+    }
+
+    fn index(&self, own_mask: u32, other_mask: u32) -> usize {
+        //of all symmetries turning the own stones into their representative the one with the lowest opponent number is used
+        let mut best: Option<(u32, usize)> = None;
+        for symmetry in 0..SYMMETRY_COUNT {
+            let own = transform_mask(VARIANT, symmetry, own_mask);
+            if best.is_some_and(|(best_own, _)| own > best_own) {
+                continue;
+            }
+            let rank = rank_combination(own, transform_mask(VARIANT, symmetry, other_mask));
+            if best.is_none_or(|(best_own, best_rank)| own < best_own || rank < best_rank) {
+                best = Some((own, rank));
+            }
+        }
+        let (own, rank) = best.unwrap_or((own_mask, 0));
+        let set = self.own_sets.binary_search(&own).unwrap_or(0);
+        set * self.other_combinations() + rank
+    }
+
+    // the stones of both players at the index
+    fn position(&self, index: usize) -> (u32, u32) {
+        let own = self.own_sets[index / self.other_combinations()];
+        (own, unrank_combination(own, index % self.other_combinations(), self.other_stones))
+    }
+
+    fn summary(&self, own_stones: u8) -> SubspaceSummary {
+        let mut summary = SubspaceSummary { own_stones, other_stones: self.other_stones, wins: 0, losses: 0, draws: 0, longest: 0 };
+        //positions with symmetric own stones are stored more than once, only the used index is counted
+        let used = (0..self.values.len()).filter(|index| {
+            let (own, other) = self.position(*index);
+            self.index(own, other) == *index
+        });
+        for index in used {
+            match EndgameValue::decode(self.values[index]) {
+                EndgameValue::Win(plies) => {
+                    summary.wins += 1;
+                    summary.longest = summary.longest.max(plies);
+                }
+                EndgameValue::Loss(plies) => {
+                    summary.losses += 1;
+                    summary.longest = summary.longest.max(plies);
+                }
+                EndgameValue::Draw => summary.draws += 1,
+            }
+        }
+        summary
+    }
+}
+
+// all sets of the given number of fields that are the smallest of their symmetric sets, in ascending order
+fn canonical_sets(stones: u8) -> Vec<u32> {
+    let mut sets = Vec::new();
+    let mut mask: u32 = (1 << stones) - 1;
+    while mask <= VARIANT.all_fields() {
+        if (0..SYMMETRY_COUNT).all(|symmetry| transform_mask(VARIANT, symmetry, mask) >= mask) {
+            sets.push(mask);
+        }
+        //next larger number with the same amount of set bits
+        let lowest = mask & mask.wrapping_neg();
+        let carried = mask + lowest;
+        mask = carried | (((mask ^ carried) >> 2) / lowest);
+    }
+    sets
+}
+
+// number of the opponent stones among the fields not taken by the own stones
+fn rank_combination(own: u32, other: u32) -> usize {
+    let free = VARIANT.all_fields() & !own;
+    fields_of_mask(other)
+        .enumerate()
+        .map(|(k, field)| BINOMIALS[(free & (field_bit(field) - 1)).count_ones() as usize][k + 1])
+        .sum()
+}
+
+fn unrank_combination(own: u32, mut rank: usize, stones: u8) -> u32 {
+    let free: Vec<u8> = fields_of_mask(VARIANT.all_fields() & !own).collect();
+    let mut mask = 0;
+    for k in (1..=stones as usize).rev() {
+        let mut position = free.len() - 1;
+        while BINOMIALS[position][k] > rank {
+            position -= 1;
+        }
+        rank -= BINOMIALS[position][k];
+        mask |= field_bit(free[position]);
+    }
+    mask
+}
+
+// what the build keeps for every position of a table besides its value
+struct Progress {
+    // the next round in which a capture into a smaller table can decide the position, 0 if there is none
+    capture_rounds: Vec<u8>,
+    // one bit for every position with a move into a position decided in the last round
+    marks: Vec<u64>,
+}
+
+impl Progress {
+    fn new(size: usize) -> Progress {
+        Progress { capture_rounds: vec![0; size], marks: vec![0; size.div_ceil(64)] }
+    }
+
+    fn mark(&mut self, index: usize) {
+        self.marks[index / 64] |= 1 << (index % 64);
+    }
+
+    fn is_marked(&self, index: usize) -> bool {
+        self.marks[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn clear_marks(&mut self) {
+        self.marks.fill(0);
+    }
+}
+
+fn file_name(own_stones: u8, other_stones: u8) -> String {
+    format!("endgame_{}_{}.db", own_stones, other_stones)
+}
+
+// win/loss/draw tables of move phase positions with 3 to 9 stones on each side, built by retrograde analysis
+#[derive(Default)]
+pub struct EndgameDatabase {
+    subspaces: HashMap<(u8, u8), Subspace>,
+}
+
+impl EndgameDatabase {
+    pub fn new() -> EndgameDatabase {
+        EndgameDatabase::default()
+    }
+
+    // reads all tables found in the directory
+    pub fn load(directory: &Path) -> io::Result<EndgameDatabase> {
+        let mut database = EndgameDatabase::new();
+        for own_stones in MIN_STONES..=VARIANT.stones() {
+            for other_stones in MIN_STONES..=VARIANT.stones() {
+                let path = directory.join(file_name(own_stones, other_stones));
+                if !path.exists() {
+                    continue;
+                }
+                let mut subspace = Subspace::new(own_stones, other_stones);
+                let values = fs::read(&path)?;
+                if values.len() != subspace.values.len() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} has the wrong size", path.display())))
+                }
+                subspace.values = values;
+                database.subspaces.insert((own_stones, other_stones), subspace);
+            }
+        }
+        Ok(database)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subspaces.is_empty()
+    }
+
+    // solves all tables with up to max_stones on each side that aren't loaded yet and writes them to the directory.
+    // the tables with less stones are needed for the captures, so they are solved first
+    pub fn build(&mut self, directory: &Path, max_stones: u8, mut report: impl FnMut(&SubspaceSummary)) -> io::Result<()> {
+        let max_stones = max_stones.clamp(MIN_STONES, VARIANT.stones());
+        for total in 2 * MIN_STONES..=2 * max_stones {
+            for own_stones in MIN_STONES..=total / 2 {
+                let other_stones = total - own_stones;
+                if other_stones > max_stones || (self.subspaces.contains_key(&(own_stones, other_stones)) && self.subspaces.contains_key(&(other_stones, own_stones))) {
+                    continue;
+                }
+                self.solve(own_stones, other_stones);
+                for key in [(own_stones, other_stones), (other_stones, own_stones)] {
+                    let subspace = &self.subspaces[&key];
+                    fs::write(directory.join(file_name(key.0, key.1)), &subspace.values)?;
+                    report(&subspace.summary(key.0));
+                    if key.0 == key.1 {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // a move without capture leads to the table with swapped stone counts, so both are solved together.
+    // in round n every position that is decided in n plies gets its value n + 1:
+    // a win if a move leads to a loss in n-1 plies, a loss if all moves lead to wins in at most n-1 plies.
+    // only positions with a move into a position of the last round or with a capture deciding in this round can change,
+    // the positions of the last round are found by their value, so the build needs no lists of positions
+    fn solve(&mut self, own_stones: u8, other_stones: u8) {
+        let keys = if own_stones == other_stones {
+            vec![(own_stones, other_stones)]
+        } else {
+            vec![(own_stones, other_stones), (other_stones, own_stones)]
+        };
+        let mut progress = Vec::new();
+        for key in &keys {
+            let subspace = Subspace::new(key.0, key.1);
+            progress.push(Progress::new(subspace.values.len()));
+            self.subspaces.insert(*key, subspace);
+        }
+        //blocked positions are lost right away, captures into the smaller tables decide at known rounds
+        let mut last_capture_round = 0;
+        for (slot, key) in keys.iter().enumerate() {
+            for index in 0..progress[slot].capture_rounds.len() {
+                let (own, other) = self.subspaces[key].position(index);
+                if self.subspaces[key].index(own, other) != index {
+                    continue;
+                }
+                match self.capture_rounds(own, other) {
+                    None => self.set_value(*key, index, 1),
+                    Some(rounds) => {
+                        last_capture_round = rounds.into_iter().flatten().fold(last_capture_round, u8::max);
+                        progress[slot].capture_rounds[index] = rounds.into_iter().flatten().min().unwrap_or(0);
+                    }
+                }
+            }
+        }
+        for round in 1..=MAX_PLIES {
+            //the positions decided in the last round have the value round
+            for key in &keys {
+                for index in 0..self.subspaces[key].values.len() {
+                    if self.subspaces[key].values[index] != round {
+                        continue;
+                    }
+                    for (previous_key, previous) in self.predecessors(*key, index) {
+                        if let Some(slot) = keys.iter().position(|key| *key == previous_key) {
+                            progress[slot].mark(previous);
+                        }
+                    }
+                }
+            }
+            //positions decided in this round get round + 1, which the other candidates don't take as decided yet
+            let mut decided = false;
+            for (slot, key) in keys.iter().enumerate() {
+                for index in 0..progress[slot].capture_rounds.len() {
+                    let captures_now = progress[slot].capture_rounds[index] == round;
+                    if !(progress[slot].is_marked(index) || captures_now) || self.subspaces[key].values[index] != 0 {
+                        continue;
+                    }
+                    let (own, other) = self.subspaces[key].position(index);
+                    match self.solve_position(own, other, round) {
+                        Some(value) => {
+                            self.set_value(*key, index, value);
+                            decided = true;
+                        }
+                        //the longest capture into a won position decides later
+                        None if captures_now => {
+                            let later = self.capture_rounds(own, other).into_iter().flatten().flatten().filter(|later| *later > round).min();
+                            progress[slot].capture_rounds[index] = later.unwrap_or(0);
+                        }
+                        None => {}
+                    }
+                }
+                progress[slot].clear_marks();
+            }
+            if !decided && round >= last_capture_round {
+                break;
+            }
+        }
+    }
+
+    fn set_value(&mut self, key: (u8, u8), index: usize, value: u8) {
+        if let Some(subspace) = self.subspaces.get_mut(&key) {
+            subspace.values[index] = value;
+        }
+    }
+
+    // the rounds in which captures into the smaller tables can decide the position:
+    // after the shortest capture into a lost position and after the longest capture into a won one.
+    // None if the player to move is blocked
+    fn capture_rounds(&self, own: u32, other: u32) -> Option<[Option<u8>; 2]> {
+        let gameboard = GameBoard::from_masks(VARIANT, own, other, Player::White);
+        let turns = gameboard.possible_turns(Player::White);
+        if turns.is_empty() {
+            return None
+        }
+        let mut shortest_loss: Option<u8> = None;
+        let mut longest_win: Option<u8> = None;
+        for turn in turns.iter().filter(|turn| turn.remove.is_some()) {
+            let Ok(next) = gameboard.move_simulator(*turn) else { continue };
+            let next_own = next.get_stone_mask(Player::Black);
+            let value = if next_own.count_ones() < MIN_STONES as u32 {
+                EndgameValue::Loss(0)
+            } else {
+                self.lookup(next_own, next.get_stone_mask(Player::White)).map_or(EndgameValue::Draw, EndgameValue::decode)
+            };
+            match value {
+                EndgameValue::Loss(plies) => shortest_loss = Some(shortest_loss.map_or(plies, |shortest| shortest.min(plies))),
+                EndgameValue::Win(plies) => longest_win = Some(longest_win.map_or(plies, |longest| longest.max(plies))),
+                EndgameValue::Draw => {}
+            }
+        }
+        Some([shortest_loss.map(|plies| plies + 1), longest_win.map(|plies| plies + 1)])
+    }
+
+    // the positions of the other table with a move without capture into the given one
+    fn predecessors(&self, key: (u8, u8), index: usize) -> Vec<((u8, u8), usize)> {
+        let (own, other) = self.subspaces[&key].position(index);
+        let previous_key = (key.1, key.0);
+        let Some(previous) = self.subspaces.get(&previous_key) else { return Vec::new() };
+        //the moves of the player who just moved lead back to the fields the stones came from
+        let gameboard = GameBoard::from_masks(VARIANT, other, own, Player::White);
+        gameboard
+            .possile_moves_vector(Player::White)
+            .into_iter()
+            .filter_map(|backwards| {
+                let previous_own = other ^ field_bit(backwards.origin) ^ field_bit(backwards.destination);
+                let before = GameBoard::from_masks(VARIANT, previous_own, own, Player::White);
+                let forward = MillMove::new(Player::White, &before, backwards.destination, backwards.origin);
+                (!forward.closes_mill(&before)).then(|| (previous_key, previous.index(previous_own, own)))
+            })
+            .collect()
+    }
+
+    // the stored value of the position if it is decided in the given number of plies
+    fn solve_position(&self, own: u32, other: u32, round: u8) -> Option<u8> {
+        let gameboard = GameBoard::from_masks(VARIANT, own, other, Player::White);
+        let turns = gameboard.possible_turns(Player::White);
+        let mut all_wins = true;
+        let mut longest_win = 0;
+        for turn in turns {
+            let Ok(next) = gameboard.move_simulator(turn) else { continue };
+            let next_own = next.get_stone_mask(Player::Black);
+            let next_other = next.get_stone_mask(Player::White);
+            let value = if next_own.count_ones() < MIN_STONES as u32 {
+                EndgameValue::Loss(0)
+            } else {
+                self.lookup(next_own, next_other).map_or(EndgameValue::Draw, EndgameValue::decode)
+            };
+            match value {
+                EndgameValue::Loss(plies) if plies < round => return Some(plies + 2),
+                EndgameValue::Win(plies) if plies < round => longest_win = longest_win.max(plies),
+                _ => all_wins = false,
+            }
+        }
+        all_wins.then_some(longest_win + 2)
+    }
+
+    fn lookup(&self, own: u32, other: u32) -> Option<u8> {
+        let subspace = self.subspaces.get(&(own.count_ones() as u8, other.count_ones() as u8))?;
+        Some(subspace.values[subspace.index(own, other)])
+    }
+
+    // the result of a move phase position, None if it isn't part of the loaded tables
+    pub fn probe(&self, gameboard: &GameBoard) -> Option<EndgameValue> {
+        let opponent = get_other_player(gameboard.turn);
+        //the placing rules don't matter once all stones are placed
+        let rules = Rules { lasker: false, ..gameboard.rules };
+        if gameboard.variant != VARIANT || rules != Rules::new(VARIANT) || gameboard.gamephase != Phase::Move || gameboard.pending_removals > 0
+            || gameboard.get_stones_in_hand(gameboard.turn) > 0 || gameboard.get_stones_in_hand(opponent) > 0 {
+            return None
+        }
+        self.lookup(gameboard.get_stone_mask(gameboard.turn), gameboard.get_stone_mask(opponent)).map(EndgameValue::decode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combinations_rank_and_unrank() {
+        for own in [0b111, 0b1_0000_0001_0000_0001, 0b111 << 21] {
+            let free = VARIANT.all_fields() & !own;
+            let count = BINOMIALS[free.count_ones() as usize][3];
+            let mut seen = vec![false; count];
+            //every set of three free fields gets its own rank below the number of combinations
+            for first in fields_of_mask(free) {
+                for second in fields_of_mask(free).filter(|field| *field > first) {
+                    for third in fields_of_mask(free).filter(|field| *field > second) {
+                        let other = field_bit(first) | field_bit(second) | field_bit(third);
+                        let rank = rank_combination(own, other);
+                        assert!(rank < count);
+                        assert!(!seen[rank]);
+                        seen[rank] = true;
+                        assert_eq!(unrank_combination(own, rank, 3), other);
+                    }
+                }
+            }
+            assert!(seen.iter().all(|seen| *seen));
+        }
+    }
+
+    #[test]
+    fn subspace_index_is_the_same_for_symmetric_positions() {
+        let subspace = Subspace::new(3, 4);
+        for index in (0..subspace.values.len()).step_by(97) {
+            let (own, other) = subspace.position(index);
+            assert_eq!(own & other, 0);
+            assert_eq!((own.count_ones(), other.count_ones()), (3, 4));
+            let canonical = subspace.index(own, other);
+            assert!(canonical < subspace.values.len());
+            //the stored position of the used index is one of the symmetric ones
+            let (canonical_own, canonical_other) = subspace.position(canonical);
+            assert!((0..SYMMETRY_COUNT).any(|symmetry| {
+                transform_mask(VARIANT, symmetry, own) == canonical_own && transform_mask(VARIANT, symmetry, other) == canonical_other
+            }));
+            for symmetry in 0..SYMMETRY_COUNT {
+                assert_eq!(subspace.index(transform_mask(VARIANT, symmetry, own), transform_mask(VARIANT, symmetry, other)), canonical);
+            }
+        }
+    }
+}
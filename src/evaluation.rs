@@ -0,0 +1,266 @@
+use crate::datastructures::*;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+// the weight of every term of the evaluation in one phase of the game,
+// each term is counted for both players and the difference is weighted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PhaseWeights {
+    // stones on the board
+    pub material: i32,
+    pub stones_in_hand: i32,
+    // moves of stones on the board
+    pub mobility: i32,
+    // mills standing on the board
+    pub closed_mills: i32,
+    // fields a stone can be placed on or moved to that close a mill
+    pub closeable_mills: i32,
+    // while placing fields that open two mills at once, while moving stones of a mill that close another one when they leave it
+    pub double_mills: i32,
+    // stones that can't move because all their neighbours are taken
+    pub blocked_stones: i32,
+}
+
+// the weights of the placing and the moving phase
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Weights {
+    pub place: PhaseWeights,
+    pub moving: PhaseWeights,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            place: PhaseWeights { material: 100, stones_in_hand: 100, mobility: 1, closed_mills: 10, closeable_mills: 15, double_mills: 25, blocked_stones: -3 },
+            moving: PhaseWeights { material: 100, stones_in_hand: 100, mobility: 5, closed_mills: 10, closeable_mills: 20, double_mills: 40, blocked_stones: -5 },
+        }
+    }
+}
+
+pub const TERM_COUNT: usize = 7;
+// the names of the terms in weight files
+pub const TERMS: [&str; TERM_COUNT] = ["material", "stones_in_hand", "mobility", "closed_mills", "closeable_mills", "double_mills", "blocked_stones"];
+
+impl PhaseWeights {
+    fn term(&mut self, name: &str) -> Option<&mut i32> {
+        match name {
+            "material" => Some(&mut self.material),
+            "stones_in_hand" => Some(&mut self.stones_in_hand),
+            "mobility" => Some(&mut self.mobility),
+            "closed_mills" => Some(&mut self.closed_mills),
+            "closeable_mills" => Some(&mut self.closeable_mills),
+            "double_mills" => Some(&mut self.double_mills),
+            "blocked_stones" => Some(&mut self.blocked_stones),
+            _ => None,
+        }
+    }
+
+    // the weights in the order of TERMS
+    pub fn values(&self) -> [i32; TERM_COUNT] {
+        [self.material, self.stones_in_hand, self.mobility, self.closed_mills, self.closeable_mills, self.double_mills, self.blocked_stones]
+    }
+
+    pub fn from_values(values: [i32; TERM_COUNT]) -> PhaseWeights {
+        let [material, stones_in_hand, mobility, closed_mills, closeable_mills, double_mills, blocked_stones] = values;
+        PhaseWeights { material, stones_in_hand, mobility, closed_mills, closeable_mills, double_mills, blocked_stones }
+    }
+}
+
+#[derive(Debug)]
+pub enum WeightsError {
+    Io(io::Error),
+    InvalidLine { line: usize, content: String },
+    UnknownWeight { line: usize, name: String },
+    InvalidValue { line: usize, value: String },
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeightsError::Io(err) => write!(f, "{}", err),
+            WeightsError::InvalidLine { line, content } => write!(f, "expected 'name = value' in line {}, found '{}'", line, content),
+            WeightsError::UnknownWeight { line, name } => write!(f, "unknown weight {} in line {}", name, line),
+            WeightsError::InvalidValue { line, value } => write!(f, "invalid value {} in line {}, expected a whole number", value, line),
+        }
+    }
+}
+
+impl Weights {
+    // reads a weight file, see FromStr
+    pub fn load(path: &Path) -> Result<Weights, WeightsError> {
+        fs::read_to_string(path).map_err(WeightsError::Io)?.parse()
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    fn phases(&mut self, prefix: Option<&str>) -> Option<Vec<&mut PhaseWeights>> {
+        match prefix {
+            None => Some(vec![&mut self.place, &mut self.moving]),
+            Some("place") => Some(vec![&mut self.place]),
+            Some("move") => Some(vec![&mut self.moving]),
+            Some(_) => None,
+        }
+    }
+}
+
+// one weight per line as "place.mobility = 2" or "move.mobility = 5", without a phase the weight is used in both.
+// weights that aren't given keep their default, '#' starts a comment
+impl FromStr for Weights {
+    type Err = WeightsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Weights::default();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = line.split_once('=').ok_or_else(|| WeightsError::InvalidLine { line: number + 1, content: line.to_string() })?;
+            let (name, value) = (name.trim(), value.trim());
+            let value = value.parse::<i32>().map_err(|_| WeightsError::InvalidValue { line: number + 1, value: value.to_string() })?;
+            let (prefix, term) = match name.split_once('.') {
+                Some((prefix, term)) => (Some(prefix), term),
+                None => (None, name),
+            };
+            let unknown = || WeightsError::UnknownWeight { line: number + 1, name: name.to_string() };
+            for phase in weights.phases(prefix).ok_or_else(unknown)? {
+                *phase.term(term).ok_or_else(unknown)? = value;
+            }
+        }
+        Ok(weights)
+    }
+}
+
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (prefix, phase) in [("place", &self.place), ("move", &self.moving)] {
+            for (term, value) in TERMS.iter().zip(phase.values()) {
+                writeln!(f, "{}.{} = {}", prefix, term, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// the counts of every term for one player
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Features {
+    pub material: i32,
+    pub stones_in_hand: i32,
+    pub mobility: i32,
+    pub closed_mills: i32,
+    pub closeable_mills: i32,
+    pub double_mills: i32,
+    pub blocked_stones: i32,
+}
+
+impl Features {
+    pub fn of(board: &GameBoard, color: Player) -> Features {
+        let topology = board.variant.topology();
+        let own = board.get_stone_mask(color);
+        let free = board.get_free_mask();
+        //the other stones of a line through the field are all the player's
+        let completes = |mask: u32, field: u8| topology.mills_of[field as usize].iter().any(|mill| *mill != 0 && mill & !field_bit(field) & !mask == 0);
+        let placing = board.get_stones_in_hand(color) > 0;
+        let flying = board.can_fly(color);
+        let closeable_mills = if placing {
+            fields_of_mask(free).filter(|field| completes(own, *field)).count()
+        } else {
+            board.possible_mill_amount(color) as usize
+        };
+        let double_mills = if placing {
+            //two lines with one own stone each and nothing else, both threaten a mill after the placement
+            fields_of_mask(free)
+                .filter(|field| {
+                    let rest = !field_bit(*field);
+                    topology.mills_of[*field as usize].iter().filter(|mill| **mill != 0 && (*mill & rest & own).count_ones() == 1 && *mill & rest & !(own | free) == 0).count() >= 2
+                })
+                .count()
+        } else {
+            fields_of_mask(own)
+                .filter(|stone| board.mill_checker(*stone))
+                .filter(|stone| {
+                    let targets = if flying { free } else { topology.neighbours[*stone as usize] & free };
+                    fields_of_mask(targets).any(|target| completes(own & !field_bit(*stone), target))
+                })
+                .count()
+        };
+        let blocked_stones = if flying { 0 } else { fields_of_mask(own).filter(|stone| topology.neighbours[*stone as usize] & free == 0).count() };
+        Features {
+            material: own.count_ones() as i32,
+            stones_in_hand: board.get_stones_in_hand(color) as i32,
+            mobility: board.possible_moves_amount(color) as i32,
+            closed_mills: topology.mills[..topology.mill_count].iter().filter(|mill| *mill & own == **mill).count() as i32,
+            closeable_mills: closeable_mills as i32,
+            double_mills: double_mills as i32,
+            blocked_stones: blocked_stones as i32,
+        }
+    }
+
+    // the counts in the order of TERMS
+    pub fn values(&self) -> [i32; TERM_COUNT] {
+        [self.material, self.stones_in_hand, self.mobility, self.closed_mills, self.closeable_mills, self.double_mills, self.blocked_stones]
+    }
+}
+
+// the weights used in the phase of the board
+pub fn phase_weights(board: &GameBoard, weights: &Weights) -> PhaseWeights {
+    match board.gamephase {
+        Phase::Place => weights.place,
+        Phase::Move => weights.moving,
+    }
+}
+
+// heuristic score of a position from the view of the player, positive if the player stands better
+pub fn evaluate(board: &GameBoard, player: Player, weights: &Weights) -> i32 {
+    let own = Features::of(board, player).values();
+    let other = Features::of(board, get_other_player(player)).values();
+    phase_weights(board, weights).values().iter().zip(own.iter().zip(other.iter())).map(|(weight, (own, other))| weight * (own - other)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_file_round_trip() {
+        let mut tuned = Weights::default();
+        tuned.place.mobility = 7;
+        tuned.moving.blocked_stones = -12;
+        let directory = std::env::temp_dir().join(format!("muehlespiel-weights-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("weights.txt");
+        tuned.write_to_file(&path).unwrap();
+        assert_eq!(Weights::load(&path).unwrap(), tuned);
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(matches!(Weights::load(&path), Err(WeightsError::Io(_))));
+    }
+
+    #[test]
+    fn weights_by_phase() {
+        let weights: Weights = "# tuned by hand\nmobility = 3\n\nmove.double_mills = -40  # no double mills\n place.material=90".parse().unwrap();
+        assert_eq!((weights.place.mobility, weights.moving.mobility), (3, 3));
+        assert_eq!((weights.place.double_mills, weights.moving.double_mills), (Weights::default().place.double_mills, -40));
+        assert_eq!((weights.place.material, weights.moving.material), (90, Weights::default().moving.material));
+        //weights that aren't given keep their default
+        assert_eq!("".parse::<Weights>().unwrap(), Weights::default());
+    }
+
+    #[test]
+    fn invalid_weights() {
+        let error = |text: &str| text.parse::<Weights>().unwrap_err();
+        assert!(matches!(error("mobility"), WeightsError::InvalidLine { line: 1, .. }));
+        assert!(matches!(error("material = 100\nmobility ="), WeightsError::InvalidValue { line: 2, ref value } if value.is_empty()));
+        assert!(matches!(error("mobility = five"), WeightsError::InvalidValue { line: 1, ref value } if value == "five"));
+        assert!(matches!(error("mobility = 2.5"), WeightsError::InvalidValue { line: 1, .. }));
+        assert!(matches!(error("\n= 4"), WeightsError::UnknownWeight { line: 2, .. }));
+        assert!(matches!(error("speed = 4"), WeightsError::UnknownWeight { line: 1, ref name } if name == "speed"));
+        assert!(matches!(error("fly.mobility = 4"), WeightsError::UnknownWeight { line: 1, .. }));
+        assert_eq!(error("mobility = five").to_string(), "invalid value five in line 1, expected a whole number");
+    }
+}
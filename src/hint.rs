@@ -0,0 +1,129 @@
+use crate::datastructures::*;
+use crate::notation::field_name;
+use crate::search::*;
+use crate::transposition::TranspositionTable;
+use std::fmt;
+
+// the move the engine recommends to the player to move, with reasons a beginner can follow
+#[derive(Clone)]
+pub struct Hint {
+    pub millmove: MillMove,
+    // the score of the search, seen from the player to move
+    pub score: i32,
+    pub reasons: Vec<String>,
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (score {}): {}", self.millmove, self.score, self.reasons.join(", "))
+    }
+}
+
+// searches the best move of the player to move within the limits and explains it, None if the game is over
+pub fn hint(gameboard: &GameBoard, limits: SearchLimits, table: &mut TranspositionTable) -> Option<Hint> {
+    let result = find_best_move(gameboard, gameboard.turn, limits, table)?;
+    Some(Hint { millmove: result.millmove, score: result.score, reasons: explain(gameboard, result.millmove) })
+}
+
+// the reasons for a move: mills it closes, opens or blocks, stones it takes and opponent stones it shuts in
+pub fn explain(gameboard: &GameBoard, millmove: MillMove) -> Vec<String> {
+    let Ok(after) = gameboard.move_simulator(millmove) else { return vec![String::from("not a legal move")] };
+    let player = millmove.turn;
+    let opponent = get_other_player(player);
+    let mut reasons = Vec::new();
+    if millmove.is_removal() {
+        if let Some(stone) = millmove.remove {
+            reasons.push(taking_reason(gameboard, opponent, stone));
+        }
+        return reasons
+    }
+    //the lines through the destination that the stone completes
+    if after.mill_checker(millmove.destination) {
+        for line in mill_lines(&after, player, millmove.destination) {
+            reasons.push(format!("closes a mill on {}", line_name(line, gameboard)));
+        }
+    }
+    if let Some(stone) = millmove.remove {
+        reasons.push(taking_reason(gameboard, opponent, stone));
+    }
+    //the opponent could have closed a mill on the destination
+    for (_, line) in mill_threats(gameboard, opponent).into_iter().filter(|(threat, _)| threat.destination == millmove.destination) {
+        reasons.push(format!("blocks the opponent's mill on {}", line_name(line, gameboard)));
+    }
+    //a stone leaving its mill can close it again on the next move
+    if millmove.movetype == Phase::Move && gameboard.mill_checker(millmove.origin) && threat_fields(&after, player).contains(&millmove.origin) {
+        for line in mill_lines(gameboard, player, millmove.origin) {
+            reasons.push(format!("opens the mill on {} to close it again", line_name(line, gameboard)));
+        }
+    }
+    let threats_before = threat_fields(gameboard, player).len();
+    let threats_after = threat_fields(&after, player).len();
+    if threats_after >= 2 && threats_after > threats_before {
+        reasons.push(String::from("opens a double mill, the opponent can only block one of them"));
+    } else if threats_after > threats_before {
+        reasons.push(String::from("threatens to close a mill"));
+    }
+    if after.gamephase == Phase::Move && after.pending_removals == 0 && after.is_blocked(opponent) {
+        reasons.push(String::from("leaves the opponent without a move"));
+    } else if (after.gamephase == Phase::Move || after.rules.lasker) && !after.can_fly(opponent) {
+        let topology = gameboard.variant.topology();
+        let neighbours = topology.neighbours[millmove.destination as usize];
+        for stone in fields_of_mask(neighbours & after.get_stone_mask(opponent)) {
+            if topology.neighbours[stone as usize] & after.get_free_mask() == 0 {
+                reasons.push(format!("takes the last free neighbour of the stone on {}", field_name(stone, gameboard.variant)));
+            }
+        }
+    }
+    if reasons.is_empty() {
+        reasons.push(String::from("no direct threats, it leaves the best position the search found"));
+    }
+    reasons
+}
+
+// every move of the player that closes a mill with the line it closes, a line reached by several moves is listed once
+fn mill_threats(gameboard: &GameBoard, player: Player) -> Vec<(MillMove, u32)> {
+    let mut threats: Vec<(MillMove, u32)> = Vec::new();
+    for millmove in gameboard.possible_turns(player).into_iter().filter(|millmove| millmove.closes_mill(gameboard)) {
+        let Ok(next) = gameboard.move_simulator_any_turn(MillMove { remove: None, ..millmove }) else { continue };
+        for line in mill_lines(&next, player, millmove.destination) {
+            if !threats.iter().any(|(_, known)| *known == line) {
+                threats.push((millmove, line));
+            }
+        }
+    }
+    threats
+}
+
+// the fields the player could close a mill on with the next move
+fn threat_fields(gameboard: &GameBoard, player: Player) -> Vec<u8> {
+    let mut fields: Vec<u8> = Vec::new();
+    for (millmove, _) in mill_threats(gameboard, player) {
+        if !fields.contains(&millmove.destination) {
+            fields.push(millmove.destination);
+        }
+    }
+    fields
+}
+
+// the mill lines through the field that the stones of the player fill completely
+fn mill_lines(gameboard: &GameBoard, player: Player, field: u8) -> Vec<u32> {
+    let own = gameboard.get_stone_mask(player);
+    gameboard.variant.topology().mills_of[field as usize].iter().copied().filter(|line| *line != 0 && own & line == *line).collect()
+}
+
+// the stone is one of a line the opponent was about to close, or the stone that would close it
+fn taking_reason(gameboard: &GameBoard, opponent: Player, stone: u8) -> String {
+    let threatening = mill_threats(gameboard, opponent).iter().any(|(threat, line)| line & field_bit(stone) != 0 || threat.origin == stone);
+    if threatening {
+        format!("takes the stone on {} that threatened a mill", field_name(stone, gameboard.variant))
+    } else {
+        format!("takes the stone on {}", field_name(stone, gameboard.variant))
+    }
+}
+
+// the fields of a mill line in the notation of the moves, sorted like a7-d7-g7
+fn line_name(line: u32, gameboard: &GameBoard) -> String {
+    let mut names: Vec<String> = fields_of_mask(line).map(|field| field_name(field, gameboard.variant)).collect();
+    names.sort();
+    names.join("-")
+}
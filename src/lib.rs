@@ -0,0 +1,32 @@
+// Mühle (Nine Men's Morris) and its variants as a library: the board with its rules,
+// moves and their notation, whole games between agents, the search, endgame tables and the engine protocol.
+// the binaries for interactive play, batch analysis and the engine protocol only parse their arguments
+mod datastructures;
+mod difficulty;
+mod millgame;
+mod notation;
+mod rng;
+mod rules;
+mod symmetry;
+mod transposition;
+mod variant;
+pub mod agent;
+pub mod analysis;
+pub mod endgame;
+pub mod evaluation;
+pub mod hint;
+pub mod mcts;
+pub mod protocol;
+pub mod record;
+pub mod search;
+pub mod tuning;
+
+pub use datastructures::{decode_phase, decode_player, field_bit, fields_of_mask, get_other_player, BoardError, GameBoard, MillMove, MoveError, Phase, Player, PositionError};
+pub use difficulty::Difficulty;
+pub use millgame::MillGame;
+pub use notation::{field_name, parse_field};
+pub use rng::Rng;
+pub use rules::{BlockedOutcome, Rules};
+pub use symmetry::{inverse as inverse_symmetry, transform_field, transform_mask, SYMMETRY_COUNT};
+pub use transposition::{TranspositionTable, DEFAULT_TABLE_MB};
+pub use variant::{ring_field, Topology, Variant};
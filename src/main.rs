@@ -1,52 +1,443 @@
-mod datastructures;
-use std::str::FromStr;
-use std::fs::File;
-use std::io::{Write, BufReader, BufRead, Error};
-use std::io::prelude::*;
 use std::env;
-use datastructures::*;
-mod millgame;
-use millgame::*;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Instant;
+use muehlespiel::agent::agent_from_name;
+use muehlespiel::endgame::{EndgameDatabase, EndgameValue};
+use muehlespiel::evaluation::Weights;
+use muehlespiel::tuning::*;
+use muehlespiel::record::{parse_records, GameRecord, GameResult};
+use muehlespiel::search::*;
+use muehlespiel::*;
 
 
-use crate::datastructures::GameBoard;
 
+// usage: muehlespiel <command> [arguments], see print_usage for the commands
+fn main() -> ExitCode {
+  let args: Vec<String> = env::args().collect();
+  match args.get(1).map(String::as_str) {
+    Some("play") => return run_new_game(&args[2..]),
+    Some("analyze") => return muehlespiel::analysis::main(&args[2..]),
+    Some("match") => return run_match(&args[2..]),
+    Some("best") => print_best_move(&args[2..]),
+    Some("engine") => muehlespiel::protocol::run_engine(),
+    Some("replay") => replay_games(&args[2..]),
+    Some("endgame") => run_endgame_tool(&args[2..]),
+    Some("canonical") => print_canonical(&args[2..]),
+    Some("tune") => return run_tuning(&args[2..]),
+    Some("help" | "--help" | "-h") => print_usage(),
+    Some(command) => {
+      eprintln!("Unknown command '{}'!", command);
+      print_usage();
+      return ExitCode::FAILURE;
+    }
+    None => {
+      print_usage();
+      return ExitCode::FAILURE;
+    }
+  }
+  ExitCode::SUCCESS
+}
 
+fn print_usage() {
+  println!("usage: muehlespiel <command> [arguments]");
+  println!("  play [--variant <name>] [--rules <switch,...>] [white agent] [black agent]   play a game, agents are human, random, engine or mcts[:<iterations>|:<ms>ms]");
+  println!("  play --resume <saved game> [white agent] [black agent]                       continue a saved game");
+  println!("  analyze [--input <file>] [--output <file>] [--perspective white|black]       count moves, mills and takeable stones of every position");
+  println!("  match [--variant <name>] [--rules <switch,...>] <white> <black> [games]      play games between two agents");
+  println!("  best <position> [white|black] [depth] [max nodes]                            search the best move");
+  println!("  engine                                                                       speak the engine protocol on stdin and stdout");
+  println!("  replay <record file>                                                         show recorded games move by move");
+  println!("  endgame build <directory> [max stones] | endgame probe <directory> <position>");
+  println!("  canonical <position> [move ...]                                              show the canonical form of a position");
+  println!("  tune [--output <file>] [--games <n>] [--depth <plies>] [--iterations <n>] [--match-games <n>] [--seed <n>]");
+  println!("                                                                               fit the weights to self-play games and measure the Elo change");
+  println!("  analyze also takes [--format text|jsonl|csv] [--evaluate <depth>], it reads stdin and writes stdout unless files are given");
+  println!("  play, match, best and analyze take [--weights <file>] with the weights of the evaluation");
+  println!("  play and match take [--level beginner|intermediate|expert|perfect] for the engine, perfect play uses the tables of [--endgame <directory>]");
+}
 
-fn main() {
-  read_and_write_move_information();
+// removes the option together with its value from the arguments and returns the value
+fn take_option(args: &mut Vec<String>, option: &str) -> Option<String> {
+  let position = args.iter().position(|arg| arg == option)?;
+  args.remove(position);
+  (position < args.len()).then(|| args.remove(position))
 }
 
+// removes "--weights <file>" from the arguments and loads the weights of the evaluation, the default ones without it
+fn take_weights(args: &mut Vec<String>) -> Option<Weights> {
+  let Some(path) = take_option(args, "--weights") else { return Some(Weights::default()) };
+  match Weights::load(Path::new(&path)) {
+    Ok(weights) => Some(weights),
+    Err(err) => {
+      println!("Error loading the weights from {}: {}", path, err);
+      None
+    }
+  }
+}
 
-fn run_new_game() {
-  let mut my_game= MillGame::new();
-  my_game.run();
+// the options of play and match: the game, the base limits of the engines and the level of the computer players
+struct GameOptions {
+  variant: Variant,
+  rules: Rules,
+  limits: SearchLimits,
+  level: Option<Difficulty>,
+  args: Vec<String>,
 }
 
-fn read_and_write_move_information() -> Result<(), Error> {
-  let project_directory = env::current_dir()?;
-  let input_file_path = project_directory.parent().unwrap().join("input_felder.txt");
-  let output_file_path = project_directory.parent().unwrap().join("output_felder.txt");
+// removes "--variant <name>", "--rules <switches>", "--weights <file>", "--level <level>" and "--endgame <directory>"
+// from the arguments, without them Nine Men's Morris is played with its usual rules
+fn take_game_options(args: &[String]) -> Option<GameOptions> {
+  let mut args = args.to_vec();
+  let weights = take_weights(&mut args)?;
+  let variant = match take_option(&mut args, "--variant") {
+    Some(name) => match Variant::from_name(&name) {
+      Some(variant) => variant,
+      None => {
+        println!("Unknown variant '{}'! Choose between three, six, nine and twelve.", name);
+        return None;
+      }
+    },
+    None => Variant::Nine,
+  };
+  let rules = match take_option(&mut args, "--rules") {
+    Some(text) => match Rules::parse(&text, variant) {
+      Some(rules) => rules,
+      None => {
+        println!("Unknown rules '{}'! Choose from flying, no-flying, lasker, double-removal, take-from-mills, blocked-draw and blocked-loss.", text);
+        return None;
+      }
+    },
+    None => Rules::new(variant),
+  };
+  let level = match take_option(&mut args, "--level") {
+    Some(name) => match Difficulty::from_name(&name) {
+      Some(level) => Some(level),
+      None => {
+        println!("Unknown level '{}'! Choose between beginner, intermediate, expert and perfect.", name);
+        return None;
+      }
+    },
+    None => None,
+  };
+  let database = match take_option(&mut args, "--endgame") {
+    Some(directory) => match EndgameDatabase::load(Path::new(&directory)) {
+      Ok(database) => Some(Arc::new(database)),
+      Err(err) => {
+        println!("Error loading the endgame tables: {}", err);
+        return None;
+      }
+    },
+    None => None,
+  };
+  let limits = SearchLimits { weights, database, ..SearchLimits::default() };
+  Some(GameOptions { variant, rules, limits, level, args })
+}
 
- let input_file = File::open(&input_file_path)?;
- let file_reader = BufReader::new(input_file);
+// usage: play [--variant three|six|nine|twelve] [--rules <switch,...>] [--weights <file>] [--level <level>] [--endgame <directory>] [white agent] [black agent],
+//        agents are human, random, engine or mcts[:<iterations>|:<ms>ms]. without a level it is asked for when a person plays the engine
+//        play --resume <saved game> [--weights <file>] [--level <level>] [--endgame <directory>] [white agent] [black agent]
+fn run_new_game(args: &[String]) -> ExitCode {
+  if args.first().map(String::as_str) == Some("--resume") {
+    return resume_game(&args[1..]);
+  }
+  let Some(options) = take_game_options(args) else { return ExitCode::FAILURE };
+  if options.args.is_empty() {
+    MillGame::new(options.variant, options.rules).run();
+    return ExitCode::SUCCESS;
+  }
+  let white = options.args.first().map(String::as_str).unwrap_or("human");
+  let black = options.args.get(1).map(String::as_str).unwrap_or("human");
+  match (agent_from_name(white, &options.limits), agent_from_name(black, &options.limits)) {
+    (Some(white_agent), Some(black_agent)) => {
+      let mut my_game= MillGame::with_rules(options.variant, options.rules, white_agent, black_agent);
+      if let Some(level) = options.level {
+        my_game.set_difficulty(level);
+      }
+      my_game.run();
+      ExitCode::SUCCESS
+    }
+    _ => {
+      println!("Unknown player! Choose between human, random, engine and mcts.");
+      ExitCode::FAILURE
+    }
+  }
+}
 
- let mut output_file = File::create(&output_file_path)?;
- for line in file_reader.lines(){
-  let line_content = line?;
-  let mut white_moves=0;
-  let mut white_mills=0;
-  let mut takeable_stones=0;
-    match line_content.parse::<GameBoard>() {
-      Ok(gameboard) => {
-         white_moves= gameboard.possible_moves_amount(Player::White);
-         white_mills= gameboard.possible_mill_amount(Player::White);
-         takeable_stones= gameboard.takeable_opponent_amount(Player::White);
+// the players of the saved game play on unless others are given, variant and rules are those of the record
+fn resume_game(args: &[String]) -> ExitCode {
+  let Some(GameOptions { limits, level, args, .. }) = take_game_options(args) else { return ExitCode::FAILURE };
+  let Some(path) = args.first() else {
+    println!("Please pass the file of the saved game!");
+    return ExitCode::FAILURE;
+  };
+  let record = match std::fs::read_to_string(path).map(|text| text.parse::<GameRecord>()) {
+    Ok(Ok(record)) => record,
+    Ok(Err(err)) => {
+      println!("Invalid saved game: {}", err);
+      return ExitCode::FAILURE;
+    }
+    Err(err) => {
+      println!("Error reading {}: {}", path, err);
+      return ExitCode::FAILURE;
+    }
+  };
+  let white = args.get(1).map(String::as_str).or(record.get_header("White")).unwrap_or("human");
+  let black = args.get(2).map(String::as_str).or(record.get_header("Black")).unwrap_or("human");
+  let (Some(white_agent), Some(black_agent)) = (agent_from_name(white, &limits), agent_from_name(black, &limits)) else {
+    println!("Unknown player! Choose between human, random, engine and mcts.");
+    return ExitCode::FAILURE;
+  };
+  match MillGame::resume(&record, white_agent, black_agent) {
+    Ok(mut my_game) => {
+      if let Some(level) = level {
+        my_game.set_difficulty(level);
       }
-      Err(err) => {println!("Error parsing string to gameboard!")}
+      my_game.run();
+      ExitCode::SUCCESS
+    }
+    Err(err) => {
+      println!("Invalid saved game: {}", err);
+      ExitCode::FAILURE
+    }
+  }
+}
+
+// usage: match [--variant three|six|nine|twelve] [--rules <switch,...>] [--weights <file>] [--level <level>] [--endgame <directory>] <white agent> <black agent> [games],
+//        plays the games without any output
+fn run_match(args: &[String]) -> ExitCode {
+  let Some(GameOptions { variant, rules, limits, level, args }) = take_game_options(args) else { return ExitCode::FAILURE };
+  let white = args.first().map(String::as_str).unwrap_or("engine");
+  let black = args.get(1).map(String::as_str).unwrap_or("random");
+  let games = args.get(2).and_then(|games| games.parse::<u32>().ok()).unwrap_or(10);
+  let (mut white_wins, mut black_wins, mut draws) = (0, 0, 0);
+  for _ in 0..games {
+    let (Some(white_agent), Some(black_agent)) = (agent_from_name(white, &limits), agent_from_name(black, &limits)) else {
+      println!("Unknown player! Choose between human, random, engine and mcts.");
+      return ExitCode::FAILURE;
+    };
+    let mut game = MillGame::with_rules(variant, rules, white_agent, black_agent);
+    if let Some(level) = level {
+      game.set_difficulty(level);
+    }
+    match game.play() {
+      GameResult::WhiteWins => white_wins += 1,
+      GameResult::BlackWins => black_wins += 1,
+      GameResult::Draw | GameResult::Unfinished => draws += 1,
+    }
+  }
+  println!("{} (white) vs {} (black): {} wins, {} losses, {} draws", white, black, white_wins, black_wins, draws);
+  ExitCode::SUCCESS
+}
+
+// usage: best [--weights <file>] <position or gameboard string> [white|black] [depth] [max nodes]
+fn print_best_move(args: &[String]) {
+  let mut args = args.to_vec();
+  let Some(weights) = take_weights(&mut args) else { return };
+  let gameboard = match args.first().map(|board| board.parse::<GameBoard>()) {
+    Some(Ok(gameboard)) => gameboard,
+    Some(Err(err)) => {
+      println!("Invalid position: {}", err);
+      return;
+    }
+    None => {
+      println!("Please pass a valid gameboard string!");
+      return;
+    }
+  };
+  let player = match args.get(1).map(String::as_str) {
+    Some("black") => Player::Black,
+    Some("white") => Player::White,
+    _ => gameboard.get_turn(),
+  };
+  let depth = args.get(2).and_then(|depth| depth.parse::<u8>().ok()).unwrap_or(4);
+  let nodes = args.get(3).and_then(|nodes| nodes.parse::<u64>().ok());
+  let limits = SearchLimits { nodes, weights, ..SearchLimits::depth(depth) };
+  match find_best_move(&gameboard, player, limits, &mut TranspositionTable::default()) {
+    Some(result) => {
+      println!("best move: {} (score {}, depth {}, {} nodes)", result.millmove, result.score, result.depth, result.nodes);
+    }
+    None => println!("No move found for {}, the game is already over!", decode_player(Some(player))),
+  }
+}
+
+// usage: replay <record file>, shows the games of the file move by move
+fn replay_games(args: &[String]) {
+  let Some(path) = args.first() else {
+    println!("Please pass the file of the game record!");
+    return;
+  };
+  let records = match std::fs::read_to_string(path).map(|text| parse_records(&text)) {
+    Ok(Ok(records)) => records,
+    Ok(Err(err)) => {
+      println!("Invalid game record: {}", err);
+      return;
+    }
+    Err(err) => {
+      println!("Error reading {}: {}", path, err);
+      return;
+    }
+  };
+  for record in records {
+    println!("{} (white) vs {} (black), {}", record.get_header("White").unwrap_or("?"), record.get_header("Black").unwrap_or("?"), record.get_header("Date").unwrap_or("?"));
+    let positions = match record.replay() {
+      Ok(positions) => positions,
+      Err(err) => {
+        println!("Invalid game record: {}", err);
+        return;
+      }
+    };
+    for (millmove, gameboard) in positions {
+      if let Some(millmove) = millmove {
+        println!("{} played {}", decode_player(Some(millmove.turn)), millmove);
+      }
+      gameboard.print_gameboard();
+      println!("press 'enter' for the next move");
+      let mut user_input = String::new();
+      if std::io::stdin().read_line(&mut user_input).unwrap_or(0) == 0 {
+        return;
+      }
+    }
+    println!("Result: {}", record.result);
+  }
+}
+
+// usage: canonical <position> [move ...], prints the canonical representative and the moves on it
+fn print_canonical(args: &[String]) {
+  let gameboard = match args.first().map(|board| board.parse::<GameBoard>()) {
+    Some(Ok(gameboard)) => gameboard,
+    Some(Err(err)) => {
+      println!("Invalid position: {}", err);
+      return;
+    }
+    None => {
+      println!("Please pass a valid gameboard string!");
+      return;
+    }
+  };
+  let (canonical, symmetry) = gameboard.canonical();
+  println!("canonical position: {} (symmetry {}, undone by {})", canonical.to_position(), symmetry, inverse_symmetry(symmetry));
+  for text in &args[1..] {
+    match MillMove::from_notation(text, &gameboard) {
+      Some(millmove) => println!("{} -> {}", millmove, millmove.transformed(symmetry)),
+      None => println!("Invalid move {}", text),
+    }
+  }
+}
+
+// usage: endgame build <directory> [max stones per side]
+//        endgame probe <directory> <position>
+fn run_endgame_tool(args: &[String]) {
+  let (Some(command), Some(directory)) = (args.first().map(String::as_str), args.get(1).map(Path::new)) else {
+    println!("Please pass 'build' or 'probe' and the directory of the endgame tables!");
+    return;
+  };
+  let mut database = match EndgameDatabase::load(directory) {
+    Ok(database) => database,
+    Err(err) => {
+      println!("Error loading the endgame tables: {}", err);
+      return;
+    }
+  };
+  match command {
+    "build" => {
+      let max_stones = args.get(2).and_then(|stones| stones.parse::<u8>().ok()).unwrap_or(Variant::Nine.stones());
+      let start = Instant::now();
+      let result = database.build(directory, max_stones, |summary| {
+        println!("{} vs {} stones: {} wins, {} losses, {} draws, longest result in {} plies ({:.1}s)",
+          summary.own_stones, summary.other_stones, summary.wins, summary.losses, summary.draws, summary.longest, start.elapsed().as_secs_f64());
+      });
+      if let Err(err) = result {
+        println!("Error writing the endgame tables: {}", err);
+      }
+    }
+    "probe" => match args.get(2).map(|position| position.parse::<GameBoard>()) {
+      Some(Ok(gameboard)) => match database.probe(&gameboard) {
+        Some(EndgameValue::Win(plies)) => println!("{} wins in {} plies", decode_player(Some(gameboard.get_turn())), plies),
+        Some(EndgameValue::Loss(plies)) => println!("{} loses in {} plies", decode_player(Some(gameboard.get_turn())), plies),
+        Some(EndgameValue::Draw) => println!("draw"),
+        None => println!("The position isn't part of the endgame tables!"),
+      },
+      Some(Err(err)) => println!("Invalid position: {}", err),
+      None => println!("Please pass a position!"),
+    },
+    _ => println!("Unknown command {}! Choose between build and probe.", command),
+  }
+}
+
+// usage: tune [--variant <name>] [--rules <switch,...>] [--weights <file>] [--output <file>] [--games <n>] [--depth <plies>]
+//             [--iterations <n>] [--match-games <n>] [--seed <n>]
+// plays self-play games with the weights, fits them to the results, writes the tuned weights
+// to the output file (tuned_weights.txt without it) and plays a match between the old and the tuned weights
+fn run_tuning(args: &[String]) -> ExitCode {
+  let Some(GameOptions { variant, rules, limits, mut args, .. }) = take_game_options(args) else { return ExitCode::FAILURE };
+  let weights = limits.weights;
+  let output = take_option(&mut args, "--output").unwrap_or_else(|| String::from("tuned_weights.txt"));
+  let mut options = TuningOptions { variant, rules, ..TuningOptions::default() };
+  let numbers = [("--games", &mut options.games), ("--iterations", &mut options.iterations), ("--match-games", &mut options.match_games)];
+  for (option, target) in numbers {
+    if let Some(value) = take_option(&mut args, option) {
+      match value.parse::<u32>() {
+        Ok(value) => *target = value,
+        Err(_) => {
+          eprintln!("Invalid value '{}' for {}!", value, option);
+          return ExitCode::FAILURE;
+        }
+      }
+    }
+  }
+  if let Some(value) = take_option(&mut args, "--depth") {
+    match value.parse::<u8>() {
+      Ok(depth) if (1..=MAX_DEPTH).contains(&depth) => options.depth = depth,
+      _ => {
+        eprintln!("Invalid depth '{}'!", value);
+        return ExitCode::FAILURE;
+      }
+    }
+  }
+  if let Some(value) = take_option(&mut args, "--seed") {
+    match value.parse::<u64>() {
+      Ok(seed) => options.seed = seed,
+      Err(_) => {
+        eprintln!("Invalid seed '{}'!", value);
+        return ExitCode::FAILURE;
+      }
+    }
+  }
+  if let Some(unknown) = args.first() {
+    eprintln!("Unknown argument '{}'!", unknown);
+    return ExitCode::FAILURE;
+  }
+
+  let start = Instant::now();
+  let records = generate_games(&weights, &options, |game| {
+    if game % 10 == 0 || game == options.games {
+      println!("played {} of {} self-play games ({:.1}s)", game, options.games, start.elapsed().as_secs_f64());
+    }
+  });
+  let samples = collect_samples(&records, &options);
+  if samples.is_empty() {
+    eprintln!("The games have no positions to tune with!");
+    return ExitCode::FAILURE;
+  }
+  let scale = fit_scale(&samples, &weights);
+  let error_before = prediction_error(&samples, &weights, scale);
+  let tuned = tune_weights(&samples, &weights, scale, options.iterations);
+  let error_after = prediction_error(&samples, &tuned, scale);
+  println!("{} positions, scale {:.1}, prediction error {:.5} -> {:.5}", samples.len(), scale, error_before, error_after);
+  if let Err(err) = tuned.write_to_file(Path::new(&output)) {
+    eprintln!("Error writing the weights to {}: {}", output, err);
+    return ExitCode::FAILURE;
+  }
+  println!("wrote the tuned weights to {}", output);
+
+  let result = play_match(&tuned, &weights, &options, |game| {
+    if game % 10 == 0 || game == options.match_games {
+      println!("played {} of {} match games ({:.1}s)", game, options.match_games, start.elapsed().as_secs_f64());
     }
-   let output_line_content= String::from(format!("{} {} {}", white_moves, white_mills, takeable_stones));
-   writeln!(output_file, "{}", output_line_content);
+  });
+  println!("tuned vs old weights: {} wins, {} losses, {} draws, score {:.1}%, Elo change {:+.0}",
+    result.wins, result.losses, result.draws, 100.0 * result.score(), result.elo());
+  ExitCode::SUCCESS
 }
-Ok(())
-}
\ No newline at end of file
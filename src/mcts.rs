@@ -0,0 +1,247 @@
+use crate::agent::*;
+use crate::datastructures::*;
+use crate::evaluation::*;
+use crate::rng::Rng;
+use crate::search::terminal_score;
+use std::time::{Duration, Instant};
+
+// evaluations are turned into winning chances of heuristic rollouts with this scale
+const ROLLOUT_SCALE: f64 = 400.0;
+// how many plies below the root the position of the next search is looked for to reuse the tree
+const REUSE_DEPTH: u8 = 3;
+
+// how a rollout picks its moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rollout {
+    // uniformly random moves, a rollout that doesn't finish counts as a draw
+    Random,
+    // moves taking a stone are preferred, an unfinished rollout is scored by the evaluation
+    Heuristic,
+}
+
+// budget and parameters of a Monte Carlo tree search, without iterations and time it stops after DEFAULT_ITERATIONS
+#[derive(Clone, Copy, Debug)]
+pub struct MctsLimits {
+    pub iterations: Option<u64>,
+    pub time: Option<Duration>,
+    // the constant of the UCT formula, larger values try less visited moves more often
+    pub exploration: f64,
+    pub rollout: Rollout,
+    // plies after which a rollout is stopped
+    pub rollout_plies: u32,
+    pub weights: Weights,
+}
+
+pub const DEFAULT_ITERATIONS: u64 = 2000;
+
+impl Default for MctsLimits {
+    fn default() -> Self {
+        MctsLimits {
+            iterations: Some(DEFAULT_ITERATIONS),
+            time: None,
+            exploration: std::f64::consts::SQRT_2,
+            rollout: Rollout::Heuristic,
+            rollout_plies: 60,
+            weights: Weights::default(),
+        }
+    }
+}
+
+// the move with the most visits at the root
+#[derive(Clone, Copy)]
+pub struct MctsResult {
+    pub millmove: MillMove,
+    // share of the points the player to move made in the simulations of the move
+    pub score: f64,
+    pub visits: u32,
+    // simulations of this search, and simulations below the root kept from earlier searches
+    pub iterations: u64,
+    pub reused: u32,
+}
+
+struct Node {
+    board: GameBoard,
+    // the move leading here from the parent, None at the root
+    millmove: Option<MillMove>,
+    children: Vec<Node>,
+    untried: Vec<MillMove>,
+    visits: u32,
+    // points of the player who made the move leading here
+    reward: f64,
+}
+
+impl Node {
+    fn new(board: GameBoard, millmove: Option<MillMove>, rng: &mut Rng) -> Node {
+        let mut untried = if white_score(&board).is_some() { Vec::new() } else { board.possible_turns(board.turn) };
+        //expanding in random order keeps the tree from favouring the first fields
+        for index in (1..untried.len()).rev() {
+            untried.swap(index, rng.below(index + 1));
+        }
+        Node { board, millmove, children: Vec::new(), untried, visits: 0, reward: 0.0 }
+    }
+
+    fn record(&mut self, white_points: f64) {
+        self.visits += 1;
+        self.reward += match self.millmove.map(|millmove| millmove.turn) {
+            Some(Player::Black) => 1.0 - white_points,
+            _ => white_points,
+        };
+    }
+
+    // the child to follow by the UCT formula, seen from the player to move here
+    fn select(&mut self, exploration: f64) -> Option<&mut Node> {
+        let log_visits = (self.visits.max(1) as f64).ln();
+        let uct = |child: &Node| child.reward / child.visits as f64 + exploration * (log_visits / child.visits as f64).sqrt();
+        self.children.iter_mut().max_by(|a, b| uct(a).total_cmp(&uct(b)))
+    }
+
+    // one simulation through this node, returns the points of white. the caller records them in this node
+    fn simulate(&mut self, limits: &MctsLimits, rng: &mut Rng) -> f64 {
+        let white_points = if let Some(points) = white_score(&self.board) {
+            points
+        } else if let Some(millmove) = self.untried.pop() {
+            match self.board.move_simulator(millmove) {
+                Ok(next) => {
+                    let mut child = Node::new(next, Some(millmove), rng);
+                    let points = rollout(&child.board, limits, rng);
+                    child.record(points);
+                    self.children.push(child);
+                    points
+                }
+                Err(_) => 0.5,
+            }
+        } else {
+            match self.select(limits.exploration) {
+                Some(child) => {
+                    let points = child.simulate(limits, rng);
+                    child.record(points);
+                    points
+                }
+                None => 0.5,
+            }
+        };
+        white_points
+    }
+
+    // the node of the position somewhere below this one, at most depth plies down
+    fn take_descendant(&mut self, hash: u64, depth: u8) -> Option<Node> {
+        if depth == 0 {
+            return None
+        }
+        if let Some(index) = self.children.iter().position(|child| child.board.hash() == hash) {
+            return Some(self.children.swap_remove(index))
+        }
+        self.children.iter_mut().find_map(|child| child.take_descendant(hash, depth - 1))
+    }
+}
+
+// 1 if white won, 0 if black won and 0.5 for a draw, None if the game goes on
+fn white_score(board: &GameBoard) -> Option<f64> {
+    let score = terminal_score(board, 0)?;
+    let mover_points = if score > 0 { 1.0 } else if score < 0 { 0.0 } else { 0.5 };
+    Some(match board.turn {
+        Player::White => mover_points,
+        Player::Black => 1.0 - mover_points,
+    })
+}
+
+// plays the game on from the position and returns the points of white
+fn rollout(board: &GameBoard, limits: &MctsLimits, rng: &mut Rng) -> f64 {
+    let mut board = board.clone();
+    for _ in 0..limits.rollout_plies {
+        if let Some(points) = white_score(&board) {
+            return points
+        }
+        let mut turns = board.possible_turns(board.turn);
+        if limits.rollout == Rollout::Heuristic && turns.iter().any(|turn| turn.remove.is_some()) {
+            turns.retain(|turn| turn.remove.is_some());
+        }
+        if turns.is_empty() {
+            break;
+        }
+        match board.move_simulator(turns[rng.below(turns.len())]) {
+            Ok(next) => board = next,
+            Err(_) => break,
+        }
+    }
+    match limits.rollout {
+        Rollout::Random => white_score(&board).unwrap_or(0.5),
+        Rollout::Heuristic => white_score(&board).unwrap_or_else(|| {
+            let score = evaluate(&board, Player::White, &limits.weights) as f64;
+            1.0 / (1.0 + (-score / ROLLOUT_SCALE).exp())
+        }),
+    }
+}
+
+// Monte Carlo tree search with UCT selection, the tree of the last search is kept and reused
+// if the next position was already reached in it
+pub struct Mcts {
+    limits: MctsLimits,
+    rng: Rng,
+    root: Option<Node>,
+}
+
+impl Mcts {
+    pub fn new(limits: MctsLimits, rng: Rng) -> Mcts {
+        Mcts { limits, rng, root: None }
+    }
+
+    // searches the move of the player to move, None if the game is over
+    pub fn search(&mut self, board: &GameBoard) -> Option<MctsResult> {
+        let hash = board.hash();
+        let mut root = match self.root.take() {
+            Some(root) if root.board.hash() == hash => root,
+            Some(mut root) => root.take_descendant(hash, REUSE_DEPTH).unwrap_or_else(|| Node::new(board.clone(), None, &mut self.rng)),
+            None => Node::new(board.clone(), None, &mut self.rng),
+        };
+        root.millmove = None;
+        if white_score(&root.board).is_some() || (root.untried.is_empty() && root.children.is_empty()) {
+            return None
+        }
+        let reused = root.visits;
+        let start = Instant::now();
+        let max_iterations = match (self.limits.iterations, self.limits.time) {
+            (None, None) => Some(DEFAULT_ITERATIONS),
+            (iterations, _) => iterations,
+        };
+        let mut iterations = 0;
+        while max_iterations.is_none_or(|max| iterations < max) && self.limits.time.is_none_or(|time| start.elapsed() < time) {
+            let points = root.simulate(&self.limits, &mut self.rng);
+            root.record(points);
+            iterations += 1;
+        }
+        let best = root.children.iter().enumerate().max_by_key(|(_, child)| child.visits).map(|(index, _)| index)?;
+        let child = root.children.swap_remove(best);
+        let result = MctsResult {
+            millmove: child.millmove?,
+            score: child.reward / child.visits.max(1) as f64,
+            visits: child.visits,
+            iterations,
+            reused,
+        };
+        //the position after the move is the root of the next search
+        self.root = Some(child);
+        Some(result)
+    }
+}
+
+// plays the move found by the Monte Carlo tree search
+pub struct MctsAgent {
+    mcts: Mcts,
+}
+
+impl MctsAgent {
+    pub fn new(limits: MctsLimits, rng: Rng) -> MctsAgent {
+        MctsAgent { mcts: Mcts::new(limits, rng) }
+    }
+}
+
+impl Agent for MctsAgent {
+    fn name(&self) -> String {
+        String::from("mcts")
+    }
+
+    fn choose_move(&mut self, gameboard: &GameBoard) -> Action {
+        self.mcts.search(gameboard).map(|result| result.millmove).into()
+    }
+}
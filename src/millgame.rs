@@ -1,207 +1,335 @@
+use crate::agent::*;
 use crate::datastructures::*;
+use crate::difficulty::*;
+use crate::hint::hint;
+use crate::notation::*;
+use crate::record::*;
+use crate::rules::*;
+use crate::search::SearchLimits;
+use crate::transposition::TranspositionTable;
+use crate::variant::*;
+use std::collections::HashMap;
 use std::io; 
+use std::path::Path;
 
+// plies searched for the move recommended by a hint
+const HINT_DEPTH: u8 = 5;
 
 // compiler vorschläge testen 
 //remove pub from all MillGame attributes
 pub struct MillGame {
     gameboard: GameBoard,
-    past_moves: Vec<String>,
+    past_moves: Vec<GameBoard>,
+    move_list: Vec<MillMove>,
+    undone_moves: Vec<MillMove>,
+    // how often every position of the game came up, keyed by its zobrist hash
+    repetitions: HashMap<u64, u8>,
     winner: Option<Player>,
     turn: Player,
     turn_counter: u32,
     game_over: bool,
-
+    white_agent: Box<dyn Agent>,
+    black_agent: Box<dyn Agent>,
+    // the level of computer players is asked for at the start of run unless it was set before
+    level_chosen: bool,
+    // the table of the searches for hints, made when the first hint is asked for
+    hint_table: Option<TranspositionTable>,
 }
 impl MillGame {
-    pub fn new() -> MillGame {
-        let temp_last_move: Vec<String> = Vec::new();
-        let millgame: MillGame = MillGame{
-            gameboard: GameBoard::new(),
-            past_moves: temp_last_move,
+    // a game between two people at the same terminal
+    pub fn new(variant: Variant, rules: Rules) -> MillGame {
+        MillGame::with_rules(variant, rules, Box::new(HumanAgent), Box::new(HumanAgent))
+    }
+
+    pub fn with_agents(white_agent: Box<dyn Agent>, black_agent: Box<dyn Agent>) -> MillGame {
+        MillGame::with_rules(Variant::Nine, Rules::new(Variant::Nine), white_agent, black_agent)
+    }
+
+    pub fn with_rules(variant: Variant, rules: Rules, white_agent: Box<dyn Agent>, black_agent: Box<dyn Agent>) -> MillGame {
+        let gameboard = GameBoard::with_rules(variant, rules);
+        MillGame{
+            repetitions: HashMap::from([(gameboard.hash(), 1)]),
+            gameboard,
+            past_moves: Vec::new(),
+            move_list: Vec::new(),
+            undone_moves: Vec::new(),
             winner: None,
             turn: Player::White,
             turn_counter: 0,
             game_over: false,
-        };
-        millgame
+            white_agent,
+            black_agent,
+            level_chosen: false,
+            hint_table: None,
+        }
     }
-    
-    // retrieves input from player, which stone he wants to take; if possible the stone gets taken
-    fn take_opponent_stone(&mut self, taker_board: &mut GameBoard) {
-        let mut not_valid = true;
-        let current_turn: Player = self.turn.clone(); 
-        let opponent = get_other_player(current_turn);
-        let mut index: u8; 
-        println!("You closed a Mill! Which stone do you want to delete?");
-        while not_valid {
-            index = get_user_input_as_number(); 
-            if index == 0 {
-                self.game_over = true; 
-                let temp_winner: Player = get_other_player(self.turn.clone());
-                self.winner = Some(temp_winner);
-                break; 
-            } else {
-            match self.gameboard.get_player_at(index) {
-                None => {
-                    println!("This field is empty! Please try again.");
-                    continue;
-                }
-                Some(player) if player == current_turn => {
-                    println!("Stone must be from the opponent! Please try again.");
-                    continue;
-                }
-                Some(player) if player == opponent => { 
-                    if self.gameboard.has_only_mills(player) {
-                            not_valid = false; 
-                            taker_board.del_stone_at(index);
-                            taker_board.decrement_stone_counter(player);
-                    } else {
-                        if self.gameboard.mill_checker(index) {
-                            println!("Can't take stone, because it is part of a mill! Please try again.");
-                            continue
-                        } else {
-                            println!("correct mill case");
-                            not_valid = false; 
-                            taker_board.del_stone_at(index);
-                            taker_board.decrement_stone_counter(player);
-                        }
-                    }
-                }
-                _ => {}  
-            } 
+
+    // sets the level of every player that has levels
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        for agent in [&mut self.white_agent, &mut self.black_agent] {
+            if agent.has_levels() {
+                agent.set_difficulty(difficulty);
             }
         }
-    } 
-
-    fn calc_next_gameboard(&mut self, current_move: MillMove ) -> GameBoard {
-        let mut new_board : GameBoard = self.gameboard.clone();
-        if current_move.is_valid(&new_board) {
-            match current_move.movetype {
-                Phase::Place => {
-                    new_board.set_stone_at(current_move.destination, self.turn );
-                    new_board.increment_stone_counter(self.turn); 
-                    //mill closed?
-                    if new_board.mill_checker(current_move.destination) {
-                        self.take_opponent_stone(&mut new_board);
-                    }
-                    //all stones have been placed 
-                    if new_board.total_placed_black_stones == 9 && new_board.total_placed_white_stones == 9 {
-                        new_board.set_gamephase(Phase::Move);
-                    }
-                }
-                Phase::Move => {
-                    new_board.set_stone_at(current_move.destination, self.turn);
-                    new_board.del_stone_at(current_move.origin);
-                    if new_board.mill_checker(current_move.destination) {
-                        self.take_opponent_stone(&mut new_board);
-                    }
+        self.level_chosen = true;
+    }
+
+    // asks for the level of the computer players, 'enter' keeps expert
+    fn choose_difficulty(&mut self) {
+        let levels: Vec<String> = Difficulty::ALL.iter().enumerate().map(|(number, difficulty)| format!("{} {}", number + 1, difficulty)).collect();
+        println!("Choose the level of the computer: {} (just press 'enter' for expert).", levels.join(", "));
+        loop {
+            let mut user_input = String::new();
+            //closed input keeps the default as well
+            if io::stdin().read_line(&mut user_input).unwrap_or(0) == 0 || user_input.trim().is_empty() {
+                self.set_difficulty(Difficulty::Expert);
+                return;
+            }
+            match Difficulty::from_name(&user_input) {
+                Some(difficulty) => {
+                    self.set_difficulty(difficulty);
+                    return;
                 }
+                None => println!("Unknown level! Please enter its name or number."),
             }
-        } else {
-            println!("Couldn't apply changes! Move not valid!");
         }
-        new_board
     }
 
+    // continues a saved game, the moves are played again to restore the repetition history
+    pub fn resume(record: &GameRecord, white_agent: Box<dyn Agent>, black_agent: Box<dyn Agent>) -> Result<MillGame, RecordError> {
+        let mut game = MillGame::with_agents(white_agent, black_agent);
+        game.gameboard = record.start.clone();
+        game.turn = record.start.turn;
+        game.repetitions = HashMap::from([(record.start.hash(), 1)]);
+        for (number, millmove) in record.moves.iter().enumerate() {
+            game.update_game(*millmove).map_err(|_| RecordError::IllegalMove { number: number + 1, text: millmove.to_string() })?;
+            game.game_over = game.is_game_over();
+            game.turn_counter += 1;
+        }
+        let counter_matches = record.get_header("TurnCounter").is_none_or(|counter| counter.parse::<u32>() == Ok(game.turn_counter));
+        let position_matches = record.get_header("Position").is_none_or(|position| position == game.gameboard.to_position());
+        if !counter_matches || !position_matches {
+            return Err(RecordError::StateMismatch)
+        }
+        Ok(game)
+    }
+
+    // writes the game record together with the current position and turn counter
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut record = self.to_record();
+        record.set_header("Position", &self.gameboard.to_position());
+        record.set_header("TurnCounter", &self.turn_counter.to_string());
+        record.write_to_file(path)
+    }
 
-    fn retrieve_new_millmove(&mut self)-> MillMove{
-        let turn: Player = get_other_player(self.turn.clone());
-        let current_phase : Phase = self.gameboard.gamephase.clone();
-        match current_phase {
-            Phase::Place => {
-                println!("Please enter where you want to put your stone.");
-                let current_destination: u8 = get_user_input_as_number();
-                let current_origin: u8 = 1;
-                if current_destination == 0 {
-                    self.game_over = true; 
-                    let temp_winner: Player = turn.clone();
-                    self.winner = Some(temp_winner);
+    // asks the agent of the current player for its move and applies it, returns false if no move was played
+    fn play_turn(&mut self) -> bool {
+        let agent = match self.turn {
+            Player::White => &mut self.white_agent,
+            Player::Black => &mut self.black_agent,
+        };
+        match agent.choose_move(&self.gameboard) {
+            Action::Play(new_move) if self.update_game(new_move).is_ok() => {
+                self.undone_moves.clear();
+                self.game_over = self.is_game_over();
+                self.turn_counter +=1;
+                true
+            }
+            //moves of other agents are taken back too, until it's the player's turn again
+            Action::Undo => {
+                if !self.undo() {
+                    println!("There is no move to take back!");
+                }
+                while !self.current_agent().is_interactive() && self.undo() {}
+                self.gameboard.print_gameboard();
+                false
+            }
+            Action::Redo => {
+                if !self.redo() {
+                    println!("There is no move to play again!");
                 }
-                return MillMove::new(get_other_player(turn), &self.gameboard, current_origin, current_destination);
+                while !self.game_over && !self.current_agent().is_interactive() && self.redo() {}
+                self.gameboard.print_gameboard();
+                false
             }
-            Phase::Move => {
-                println!("Please enter which stone you want to move.");
-                let current_destination: u8 = 0;
-                let current_origin: u8 = get_user_input_as_number();
-                if current_origin == 0 {
-                    self.game_over = true; 
-                    let temp_winner: Player = turn.clone();
-                    self.winner = Some(temp_winner);
-                    return MillMove::new(get_other_player(turn), &self.gameboard, current_origin, current_destination);
+            Action::Save(path) => {
+                match self.save(Path::new(&path)) {
+                    Ok(()) => println!("Saved the game to {}", path),
+                    Err(err) => println!("Could not save the game: {}", err),
                 }
-                println!("Please enter where you want to move your stone on field {}", current_origin);
-                let current_destination: u8 = get_user_input_as_number();
-                if current_destination == 0 || current_origin == 0 {
-                    self.game_over = true; 
-                    let temp_winner: Player = turn.clone();
-                    self.winner = Some(temp_winner);
+                false
+            }
+            Action::Hint => {
+                let table = self.hint_table.get_or_insert_with(TranspositionTable::default);
+                match hint(&self.gameboard, SearchLimits::depth(HINT_DEPTH), table) {
+                    Some(hint) => println!("Hint: {}", hint),
+                    None => println!("There is no move left to recommend!"),
                 }
-                return MillMove::new(get_other_player(turn), &self.gameboard, current_origin, current_destination);
+                false
+            }
+            //giving up or an illegal move loses the game
+            _ => {
+                self.game_over = true;
+                self.winner = Some(get_other_player(self.turn));
+                false
+            }
+        }
+    }
+
+    // plays the game to its end without any output
+    pub fn play(&mut self) -> GameResult {
+        while !self.game_over {
+            self.play_turn();
+        }
+        self.result()
+    }
+
+    // like play, but stops after the given number of turns, the game is unfinished then
+    pub fn play_turns(&mut self, max_turns: u32) -> GameResult {
+        while !self.game_over && self.turn_counter < max_turns {
+            self.play_turn();
+        }
+        self.result()
+    }
+
+    // takes back the last move including a taken stone, returns false if there is nothing to take back
+    pub fn undo(&mut self) -> bool {
+        let (Some(gameboard), Some(millmove)) = (self.past_moves.pop(), self.move_list.pop()) else {
+            return false
+        };
+        let hash = self.gameboard.hash();
+        if let Some(count) = self.repetitions.get_mut(&hash) {
+            *count -= 1;
+            if *count == 0 {
+                self.repetitions.remove(&hash);
             }
         }
+        self.turn = gameboard.turn;
+        self.gameboard = gameboard;
+        self.undone_moves.push(millmove);
+        self.turn_counter -= 1;
+        self.game_over = false;
+        self.winner = None;
+        true
+    }
 
+    // plays the last undone move again, returns false if there is none
+    pub fn redo(&mut self) -> bool {
+        let Some(millmove) = self.undone_moves.pop() else {
+            return false
+        };
+        if self.update_game(millmove).is_err() {
+            return false
+        }
+        self.game_over = self.is_game_over();
+        self.turn_counter += 1;
+        true
     }
 
-    fn update_game(&mut self, millmove: MillMove) {
-        self.past_moves.push(self.gameboard.board.clone());
-        self.gameboard=self.calc_next_gameboard(millmove);
-        self.turn = get_other_player(self.turn); 
+    fn current_agent(&self) -> &dyn Agent {
+        match self.turn {
+            Player::White => self.white_agent.as_ref(),
+            Player::Black => self.black_agent.as_ref(),
+        }
+    }
+
+    // applies a complete move, the game stays as it is if the move is illegal
+    fn update_game(&mut self, millmove: MillMove) -> Result<(), MoveError> {
+        millmove.validate_complete(&self.gameboard)?;
+        let next = self.gameboard.move_simulator(millmove)?;
+        self.past_moves.push(std::mem::replace(&mut self.gameboard, next));
+        self.move_list.push(millmove);
+        //the player who closed a double mill stays on turn for the second removal
+        self.turn = self.gameboard.turn;
+        *self.repetitions.entry(self.gameboard.hash()).or_insert(0) += 1;
+        Ok(())
     }
 
     fn is_game_over(&mut self)->bool {
         let temp_board = self.gameboard.clone();
-        //first condition: any player with less than 3 stones after place-phase?
-        if (temp_board.total_placed_black_stones + temp_board.total_placed_white_stones) == 18{
-            if temp_board.get_blackstones() < 3 {
-                self.winner = Some(Player::White);
-                return true 
-            } else if temp_board.get_whitestones() < 3 {
-                self.winner = Some(Player::Black);
-                return true
-            } 
+        //first condition: any player with less than 3 stones on the board and in hand?
+        if temp_board.get_blackstones() + temp_board.get_stones_in_hand(Player::Black) < 3 {
+            self.winner = Some(Player::White);
+            return true 
+        } else if temp_board.get_whitestones() + temp_board.get_stones_in_hand(Player::White) < 3 {
+            self.winner = Some(Player::Black);
+            return true
         }
-         //second condition: any player unable to move? 
-         match temp_board.gamephase {
-            Phase::Place => {}
-            Phase::Move => {
-                if !temp_board.has_moves_left(Player::White) {
-                    self.winner = Some(Player::Black);
-                    println!("Player White has no legal moves left!");   
-                     return true  
-                    } else if !temp_board.has_moves_left(Player::Black){
-                      self.winner = Some(Player::White);
-                      println!("Player Black has no legal moves left!");
-                      return true 
-                    }
-            }
+         //second condition: the placed stones fill the whole board? that's a draw
+         if temp_board.is_full() {
+            self.winner = None;
+            return true
+         }
+         //third condition: player to move unable to move? depending on the rules that player loses or it's a draw
+         if temp_board.pending_removals == 0 && temp_board.is_blocked(temp_board.turn) {
+            self.winner = match temp_board.rules.blocked {
+                BlockedOutcome::Loss => Some(get_other_player(temp_board.turn)),
+                BlockedOutcome::Draw => None,
+            };
+            return true
          }
 
-        //third condition: was the current board repeated 3 times?
-        let mut counter: u64 = 0; 
-        let board_string: String = temp_board.board.clone();  
-        for element in self.past_moves.iter() {
-            if *element == board_string {
-                counter+=1;
-            }
-            if counter >= 2 {
-                break;
-            }
-        } 
-        if counter >= 2 {
-            println!("This position was repeated 3 times! ");
-            return true 
+        //fourth condition: was the same position with the same player to move and stones in hand there 3 times?
+        if self.repetitions.get(&temp_board.hash()).is_some_and(|count| *count >= 3) {
+            self.winner = None;
+            true
         } else {
-            return false 
+            false
         }
 
     }
 
+    // the game so far with the names of the agents as players
+    pub fn to_record(&self) -> GameRecord {
+        let mut record = GameRecord::new(&self.white_agent.name(), &self.black_agent.name(), self.gameboard.variant);
+        record.start = self.past_moves.first().unwrap_or(&self.gameboard).clone();
+        record.moves = self.move_list.clone();
+        record.result = self.result();
+        record
+    }
+
+    // the outcome so far, a repeated position is a draw
+    pub fn result(&self) -> GameResult {
+        GameResult::from_winner(self.game_over, self.winner)
+    }
+
+    // asks for a file to write the record of the finished game to
+    fn offer_to_save(&self) {
+        println!("Enter a file name to save the game record or just press 'enter' to skip.");
+        let mut user_input = String::new();
+        if io::stdin().read_line(&mut user_input).is_err() || user_input.trim().is_empty() {
+            return;
+        }
+        match self.to_record().write_to_file(Path::new(user_input.trim())) {
+            Ok(()) => println!("Saved the game to {}", user_input.trim()),
+            Err(err) => println!("Could not save the game: {}", err),
+        }
+    }
+
+    // tells why a game ended that wasn't decided by the stones left
+    fn print_end_reason(&self) {
+        let board = &self.gameboard;
+        let stones_left = |color: Player| board.get_stones_of(color) + board.get_stones_in_hand(color) >= 3;
+        if !stones_left(Player::White) || !stones_left(Player::Black) {
+            return;
+        }
+        if board.is_full() {
+            println!("The board is full!");
+        } else if board.pending_removals == 0 && board.is_blocked(board.turn) {
+            match board.turn {
+                Player::White => println!("Player White has no legal moves left!"),
+                Player::Black => println!("Player Black has no legal moves left!"),
+            }
+        } else if self.repetitions.get(&board.hash()).is_some_and(|count| *count >= 3) {
+            println!("This position was repeated 3 times! ");
+        }
+    }
+
     fn print_winner(&self)-> String {
         match self.winner {
-            None => {return String::from("Its a draw! Well played on both sides.")},
-            Some(Player::Black) => {return String::from("Player Black has won the game! GGWP.")},
-            Some(Player::White) => {return String::from("Player White has won the game! GGWP.")},
+            None => String::from("Its a draw! Well played on both sides."),
+            Some(Player::Black) => String::from("Player Black has won the game! GGWP."),
+            Some(Player::White) => String::from("Player White has won the game! GGWP."),
         }
     }
 
@@ -209,65 +337,44 @@ impl MillGame {
 
 
     pub fn run(&mut self){
-        println!("Welcome to this mill game. The fields are indexed the following way:");
-        println!("Starting from the upper, middle field on the innermorst ring with 1, the indexes increment clockwise and outwarts.");
-        println!("Note that after one ring is finished, the next ring starts again at the upper middle field. Here's a visual:");
-        print_tutorial_board();
-        println!("type in anything and press 'enter' to start"); 
-        let mut user_input = String::new();
-        io::stdin().read_line(&mut user_input).expect("Error parsing user input");
+        if self.white_agent.is_interactive() || self.black_agent.is_interactive() {
+            let variant = self.gameboard.variant;
+            println!("Welcome to this mill game of {}. The fields are indexed the following way:", variant);
+            println!("Starting from the upper, middle field on the innermorst ring with 1, the indexes increment clockwise and outwarts.");
+            println!("Note that after one ring is finished, the next ring starts again at the upper middle field. Here's a visual:");
+            if variant == Variant::Nine {
+                print_tutorial_board();
+            } else {
+                let fields: Vec<String> = (1..=variant.field_count()).map(|index| format!("{}={}", index, field_name(index, variant))).collect();
+                println!("{}", fields.join(" "));
+            }
+            println!("You can also use the usual coordinates (shown next to the board) and enter whole moves like d2, a1-a4 or g7xd1.");
+            println!("type in anything and press 'enter' to start"); 
+            let mut user_input = String::new();
+            io::stdin().read_line(&mut user_input).expect("Error parsing user input");
+            if !self.level_chosen && (self.white_agent.has_levels() || self.black_agent.has_levels()) {
+                self.choose_difficulty();
+            }
+        }
+        println!("White: {}, Black: {}", self.white_agent.name(), self.black_agent.name());
         self.gameboard.print_gameboard();
         while !self.game_over {
             println!("Current gamephase: {}, Current turn: {}", decode_phase(self.gameboard.gamephase), decode_player(Some(self.turn)));
-           //update and validate upcoming move
-           let mut new_move:MillMove = self.retrieve_new_millmove();
-           if self.game_over {
-            break; 
-            }
-           while !new_move.is_valid(&self.gameboard){
-            new_move = self.retrieve_new_millmove();
-            if self.game_over {
-                break; 
+            //nothing was played if the game was saved or the player gave up
+            if !self.play_turn() {
+                continue;
             }
-           }
-           if self.game_over {
-            break; 
-           }
-           //process new move
-           self.update_game(new_move); 
-           if self.game_over {
             self.gameboard.print_gameboard();
-            break; 
-            }
-           self.gameboard.print_gameboard();
-           println!("white stones: {}, black stones: {}",self.gameboard.get_whitestones(), self.gameboard.get_blackstones());
-           self.game_over = self.is_game_over();
-           self.turn_counter +=1;
+            println!("white stones: {}, black stones: {}",self.gameboard.get_whitestones(), self.gameboard.get_blackstones());
+            println!("position: {}", self.gameboard.to_position());
         }
+        self.print_end_reason();
         println!("{} The game went on for {} rounds!", self.print_winner(), self.turn_counter);
-    }
-}
-
-pub fn get_user_input_as_number() -> u8 {
-    loop {
-        println!("Please enter a valid field number (between 1 and 24) to continue or type either 'exit' or '0' to give up and end the game.");
-        let mut user_input = String::new();
-        io::stdin().read_line(&mut user_input).expect("Error parsing user input");
-        let cleaned_input = user_input.trim();
-        if cleaned_input.eq_ignore_ascii_case("exit") {
-            return 0
-        }
-        match cleaned_input.parse::<u8>() {
-            Ok(number) => {
-                return number
-            }
-            Err(_) => {
-                println!("Invalid Input. Please try again.");
-                continue;
-            }
+        if self.white_agent.is_interactive() || self.black_agent.is_interactive() {
+            self.offer_to_save();
         }
     }
-} 
+}
 
  fn print_tutorial_board(){
     let a = 24;
@@ -315,3 +422,94 @@ pub fn get_user_input_as_number() -> u8 {
     println!("{}-----------{}-----------{}", a, b, c);
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_at(position: &str) -> MillGame {
+        let mut game = MillGame::with_agents(Box::new(HumanAgent), Box::new(HumanAgent));
+        game.gameboard = GameBoard::from_position(position).unwrap();
+        game.turn = game.gameboard.turn;
+        game.repetitions = HashMap::from([(game.gameboard.hash(), 1)]);
+        game
+    }
+
+    // plays a move in notation the way play_turn does
+    fn play(game: &mut MillGame, text: &str) {
+        let millmove = MillMove::from_notation(text, &game.gameboard).unwrap();
+        game.update_game(millmove).unwrap();
+        game.undone_moves.clear();
+        game.game_over = game.is_game_over();
+        game.turn_counter += 1;
+    }
+
+    // position, turn, phase, stones on the board, turn counter and repetitions: everything undo and redo have to restore
+    type State = (String, Player, Phase, u8, u8, u32, Vec<(u64, u8)>);
+
+    fn state(game: &MillGame) -> State {
+        let board = &game.gameboard;
+        let mut repetitions: Vec<(u64, u8)> = game.repetitions.iter().map(|(hash, count)| (*hash, *count)).collect();
+        repetitions.sort_unstable();
+        (board.to_position(), game.turn, board.gamephase, board.get_whitestones(), board.get_blackstones(), game.turn_counter, repetitions)
+    }
+
+    #[test]
+    fn full_board_is_a_draw_and_blockade_a_loss() {
+        let mut full = game_at("WBWBWBWB/BWBWBWBW/WBWBWBWB w m 0 0 0 twelve");
+        assert!(full.is_game_over());
+        assert_eq!(full.winner, None);
+        let mut blocked = game_at("EBWWWWWB/BWWWWWWW/BBBBBBBB w m 0 0 0 twelve");
+        assert!(blocked.is_game_over());
+        assert_eq!(blocked.winner, Some(Player::Black));
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_game() {
+        let mut game = MillGame::new(Variant::Nine, Rules::new(Variant::Nine));
+        let start = state(&game);
+        //white closes the mill on the upper row and takes a black stone
+        let moves = ["a7", "a1", "d7", "g1", "g7xa1", "d1", "a4", "b2"];
+        for text in moves {
+            play(&mut game, text);
+        }
+        let end = state(&game);
+        assert_eq!((game.gameboard.get_whitestones(), game.gameboard.get_blackstones()), (4, 3));
+        assert_eq!(game.move_list.len(), moves.len());
+        while game.undo() {}
+        assert_eq!(state(&game), start);
+        assert!(game.move_list.is_empty() && game.past_moves.is_empty());
+        assert_eq!(game.undone_moves.len(), moves.len());
+        while game.redo() {}
+        assert_eq!(state(&game), end);
+        let played: Vec<String> = game.move_list.iter().map(|millmove| millmove.to_string()).collect();
+        assert_eq!(played, moves);
+        //nothing is left to play again
+        assert!(!game.redo());
+    }
+
+    #[test]
+    fn third_repetition_is_a_draw_until_undone() {
+        let mask = |names: &[&str]| names.iter().map(|name| field_bit(parse_field(name, Variant::Nine).unwrap())).fold(0, |mask, bit| mask | bit);
+        let start = GameBoard::from_masks(Variant::Nine, mask(&["a7", "d5", "g1", "b4"]), mask(&["g7", "a1", "f4", "d3"]), Player::White);
+        let mut game = game_at(&start.to_position());
+        let hash = game.gameboard.hash();
+        //both players move a stone away and back twice, the start position comes up for the third time
+        for round in 1..=2 {
+            for text in ["a7-d7", "g7-g4", "d7-a7", "g4-g7"] {
+                assert!(!game.game_over);
+                play(&mut game, text);
+            }
+            assert_eq!(game.repetitions[&hash], round + 1);
+        }
+        assert!(game.game_over);
+        assert_eq!(game.winner, None);
+        assert_eq!(game.result(), GameResult::Draw);
+        //taking back the last move takes back the repetition as well
+        assert!(game.undo());
+        assert_eq!(game.repetitions[&hash], 2);
+        assert!(!game.is_game_over());
+        play(&mut game, "g4-g7");
+        assert!(game.game_over);
+    }
+}
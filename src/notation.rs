@@ -0,0 +1,173 @@
+use crate::datastructures::*;
+use crate::variant::*;
+use std::fmt;
+
+const COLUMNS: [char; 7] = ['a', 'b', 'c', 'd', 'e', 'f', 'g'];
+// direction of the fields of a ring seen from the centre, clockwise from the upper middle field;
+// the ring number is the distance from the centre
+const DIRECTIONS: [(i8, i8); 8] = [(0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1)];
+
+// column (0 = a) and row (0 = 1) of a field index, the board of the variant has 2 * rings + 1 columns and rows
+fn field_coordinates(variant: Variant, index: u8) -> (i8, i8) {
+    let rings = variant.topology().rings;
+    let centre = rings as i8;
+    //the centre field of the Three Men's Morris board follows its only ring
+    if index > rings * 8 {
+        return (centre, centre)
+    }
+    let ring = ((index - 1) / 8 + 1) as i8;
+    let (dx, dy) = DIRECTIONS[((index - 1) % 8) as usize];
+    (centre + dx * ring, centre + dy * ring)
+}
+
+// standard name of a field, e.g. 1 -> "d5" and 24 -> "a7" on the Nine Men's Morris board
+pub fn field_name(index: u8, variant: Variant) -> String {
+    let (column, row) = field_coordinates(variant, index);
+    format!("{}{}", COLUMNS[column as usize], row + 1)
+}
+
+// reads a field in standard notation ("a1".."g7" for Nine Men's Morris) or as index ("1".."24")
+pub fn parse_field(text: &str, variant: Variant) -> Option<u8> {
+    let text = text.trim().to_ascii_lowercase();
+    let field_count = variant.field_count();
+    if let Ok(index) = text.parse::<u8>() {
+        return (1..=field_count).contains(&index).then_some(index)
+    }
+    let mut chars = text.chars();
+    let letter = chars.next()?;
+    let column = COLUMNS.iter().position(|c| *c == letter)? as i8;
+    let row = chars.next()?.to_digit(10)? as i8 - 1;
+    if chars.next().is_some() {
+        return None
+    }
+    (1..=field_count).find(|index| field_coordinates(variant, *index) == (column, row))
+}
+
+impl GameBoard {
+    // prints the board of any variant on its grid of fields, lines are drawn between all neighbouring fields
+    pub(crate) fn print_grid(&self) {
+        let variant = self.variant;
+        let size = 2 * variant.topology().rings as usize + 1;
+        //every field takes 4 columns and 2 rows of characters
+        let mut canvas = vec![vec![' '; 4 * (size - 1) + 1]; 2 * (size - 1) + 1];
+        let position = |index: u8| {
+            let (column, row) = field_coordinates(variant, index);
+            (4 * column as usize, 2 * (size - 1 - row as usize))
+        };
+        for index in 1..=variant.field_count() {
+            let (x, y) = position(index);
+            for neighbour in fields_of_mask(variant.topology().neighbours[index as usize]).filter(|neighbour| *neighbour > index) {
+                let (other_x, other_y) = position(neighbour);
+                if y == other_y {
+                    (x.min(other_x)..x.max(other_x)).for_each(|column| canvas[y][column] = '-');
+                } else if x == other_x {
+                    (y.min(other_y)..y.max(other_y)).for_each(|row| canvas[row][x] = '|');
+                } else {
+                    //diagonals only connect fields next to each other
+                    let rising = (other_x > x) == (other_y < y);
+                    canvas[(y + other_y) / 2][(x + other_x) / 2] = if rising { '/' } else { '\\' };
+                }
+            }
+        }
+        for index in 1..=variant.field_count() {
+            let (x, y) = position(index);
+            canvas[y][x] = decode_player(self.stone_at(index));
+        }
+        for (line, characters) in canvas.iter().enumerate() {
+            let label = if line % 2 == 0 { (size - line / 2).to_string() } else { String::from(" ") };
+            println!("{} {}", label, characters.iter().collect::<String>().trim_end());
+        }
+        let columns: Vec<String> = COLUMNS[..size].iter().map(|column| column.to_string()).collect();
+        println!("  {}", columns.join("   "));
+    }
+}
+
+impl MillMove {
+    // reads a move of the player to move: "d2" places, "a1-a4" moves, "g7xd1" takes a stone with the move, "xd1" only takes
+    pub fn from_notation(text: &str, gameboard: &GameBoard) -> Option<MillMove> {
+        let text = text.trim().to_ascii_lowercase();
+        let (step, remove) = match text.split_once('x') {
+            Some((step, remove)) => (step, Some(parse_field(remove, gameboard.variant)?)),
+            None => (text.as_str(), None),
+        };
+        if step.is_empty() {
+            return remove.map(|stone| MillMove::removal(gameboard.turn, gameboard, stone))
+        }
+        let (origin, destination, movetype) = match step.split_once('-') {
+            Some((origin, destination)) => (parse_field(origin, gameboard.variant)?, parse_field(destination, gameboard.variant)?, Phase::Move),
+            None => (0, parse_field(step, gameboard.variant)?, Phase::Place),
+        };
+        let millmove = MillMove::new(gameboard.turn, gameboard, origin, destination);
+        if millmove.movetype != movetype {
+            return None
+        }
+        Some(MillMove { remove, ..millmove })
+    }
+}
+
+impl fmt::Display for MillMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.is_removal() {
+            match self.movetype {
+                Phase::Place => write!(f, "{}", field_name(self.destination, self.variant))?,
+                Phase::Move => write!(f, "{}-{}", field_name(self.origin, self.variant), field_name(self.destination, self.variant))?,
+            }
+        }
+        if let Some(stone) = self.remove {
+            write!(f, "x{}", field_name(stone, self.variant))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_round_trip() {
+        for variant in Variant::ALL {
+            for index in 1..=variant.field_count() {
+                assert_eq!(parse_field(&field_name(index, variant), variant), Some(index));
+                assert_eq!(parse_field(&index.to_string(), variant), Some(index));
+            }
+        }
+        assert_eq!(field_name(1, Variant::Nine), "d5");
+        assert_eq!(field_name(24, Variant::Nine), "a7");
+        assert_eq!(parse_field(" G7 ", Variant::Nine), Some(18));
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        let cases = [
+            ("EEEEEEEE/EEEEEEEE/EEEEEEEE w p 9 9 0", "d2"),
+            ("WWEEEEEE/BBEEEEEE/EEEEEEEE w p 7 7 0", "a7xd6"),
+            ("WWWWEEEE/BBBBEEEE/EEEEEEEE w m 0 0 0", "d7-a7"),
+            //three white stones fly
+            ("WWEEEEEE/BBBBEEEE/WEEEEEEE w m 0 0 0", "d5-a1"),
+            ("WWWEEEEE/BBBBEEEE/EEEEEEEE w m 0 0 0", "g4-g1"),
+            ("WEEEEWWE/BBBBEEEE/EEEEEEEE w m 0 0 0", "d7-a7xd6"),
+            ("WWWEEEEE/BBBBEEEE/EEEEEEEE w m 0 0 1", "xd6"),
+        ];
+        for (position, text) in cases {
+            let gameboard = GameBoard::from_position(position).unwrap();
+            let millmove = MillMove::from_notation(text, &gameboard).unwrap();
+            assert_eq!(millmove.validate_complete(&gameboard), Ok(()), "{} {}", position, text);
+            assert_eq!(millmove.to_string(), text);
+            let upper = MillMove::from_notation(&text.to_ascii_uppercase(), &gameboard).unwrap();
+            assert_eq!(upper.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn malformed_moves() {
+        let placing = GameBoard::new();
+        let moving = GameBoard::from_position("WWWWEEEE/BBBBEEEE/EEEEEEEE w m 0 0 0").unwrap();
+        for text in ["", "x", "h1", "a2", "d8", "d22", "d2-", "d7x", "xx", "d7-a7"] {
+            assert!(MillMove::from_notation(text, &placing).is_none(), "{}", text);
+        }
+        for text in ["a1", "d7-", "-a7", "d7-a7-a4", "d7-a7x", "d7-a7xz9"] {
+            assert!(MillMove::from_notation(text, &moving).is_none(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use crate::datastructures::*;
+use crate::endgame::EndgameDatabase;
+use crate::evaluation::Weights;
+use crate::search::*;
+use crate::transposition::*;
+use crate::rules::Rules;
+use crate::variant::Variant;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const ENGINE_NAME: &str = "muehlespiel";
+// share of the remaining clock time spent on a single move
+const MOVES_TO_GO: u64 = 20;
+
+// line based engine protocol modeled after UCI:
+//   uci, isready, ucinewgame, quit
+//   setoption name Hash value <megabytes>
+//   setoption name EndgamePath value <directory with the endgame tables>
+//   setoption name Variant value <three|six|nine|twelve>
+//   setoption name Rules value <switches like lasker,no-flying,double-removal,take-from-mills,blocked-draw>
+//   setoption name Weights value <file with the weights of the evaluation>
+//   position (startpos | fen <position string>) [moves <move> ...]
+//   go [depth <plies>] [nodes <count>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [infinite]
+//   stop
+// a search answers with "info ..." lines for every finished depth and a final "bestmove <move>"
+pub fn run_engine() {
+    let mut engine = Engine::new();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if !engine.handle_command(&line) {
+            engine.stop_search();
+            return;
+        }
+    }
+    //end of input lets a limited search finish
+    engine.wait_for_search();
+}
+
+struct Engine {
+    variant: Variant,
+    rules: Rules,
+    gameboard: GameBoard,
+    // lent to the search thread while a search runs
+    table: Option<TranspositionTable>,
+    database: Option<Arc<EndgameDatabase>>,
+    weights: Weights,
+    stop: Arc<AtomicBool>,
+    search_thread: Option<JoinHandle<TranspositionTable>>,
+    search_limited: bool,
+}
+
+impl Engine {
+    fn new() -> Engine {
+        Engine {
+            variant: Variant::Nine,
+            rules: Rules::new(Variant::Nine),
+            gameboard: GameBoard::new(),
+            table: Some(TranspositionTable::default()),
+            database: None,
+            weights: Weights::default(),
+            stop: Arc::new(AtomicBool::new(false)),
+            search_thread: None,
+            search_limited: false,
+        }
+    }
+
+    // handles one command line, returns false once the engine should quit
+    fn handle_command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first() {
+            Some(&"uci") => {
+                println!("id name {}", ENGINE_NAME);
+                println!("option name Hash type spin default {} min 1 max 4096", DEFAULT_TABLE_MB);
+                println!("option name EndgamePath type string default <empty>");
+                println!("option name Variant type combo default nine var three var six var nine var twelve");
+                println!("option name Rules type string default <empty>");
+                println!("option name Weights type string default <empty>");
+                println!("uciok");
+            }
+            Some(&"isready") => println!("readyok"),
+            Some(&"ucinewgame") => {
+                self.wait_for_search();
+                self.gameboard = GameBoard::with_rules(self.variant, self.rules);
+                if let Some(table) = self.table.as_mut() {
+                    table.clear();
+                }
+            }
+            Some(&"setoption") => {
+                self.wait_for_search();
+                self.set_option(&words[1..]);
+            }
+            Some(&"position") => {
+                self.wait_for_search();
+                self.set_position(&words[1..]);
+            }
+            Some(&"go") => {
+                self.wait_for_search();
+                self.start_search(&words[1..]);
+            }
+            Some(&"stop") => self.stop_search(),
+            Some(&"quit") => return false,
+            Some(other) => println!("info string unknown command {}", other),
+            None => {}
+        }
+        true
+    }
+
+    fn set_position(&mut self, words: &[&str]) {
+        let moves_at = words.iter().position(|word| *word == "moves").unwrap_or(words.len());
+        let mut gameboard = match words.first() {
+            Some(&"startpos") => GameBoard::with_rules(self.variant, self.rules),
+            Some(&"fen") => match words[1..moves_at].join(" ").parse::<GameBoard>() {
+                Ok(gameboard) => gameboard,
+                Err(err) => {
+                    println!("info string invalid position: {}", err);
+                    return;
+                }
+            },
+            _ => {
+                println!("info string expected startpos or fen");
+                return;
+            }
+        };
+        //the rules set for the engine apply to positions of its variant
+        if gameboard.variant == self.variant {
+            gameboard.rules = self.rules;
+        }
+        for text in words.iter().skip(moves_at + 1) {
+            match MillMove::from_notation(text, &gameboard) {
+                Some(millmove) => match millmove.validate_complete(&gameboard).and_then(|()| gameboard.move_simulator(millmove)) {
+                    Ok(next) => gameboard = next,
+                    Err(err) => {
+                        println!("info string illegal move {}: {}", text, err);
+                        return;
+                    }
+                },
+                None => {
+                    println!("info string illegal move {}", text);
+                    return;
+                }
+            }
+        }
+        self.gameboard = gameboard;
+    }
+
+    // setoption name <name> value <value>
+    fn set_option(&mut self, words: &[&str]) {
+        let value_at = words.iter().position(|word| *word == "value").unwrap_or(words.len());
+        let name = words.get(1..value_at).map(|name| name.join(" ")).unwrap_or_default();
+        let value = words.get(value_at + 1..).map(|value| value.join(" ")).unwrap_or_default();
+        match name.to_ascii_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(size_mb) => self.table = Some(TranspositionTable::new(size_mb.clamp(1, 4096))),
+                Err(_) => println!("info string invalid hash size {}", value),
+            },
+            "endgamepath" => match EndgameDatabase::load(Path::new(&value)) {
+                Ok(database) if database.is_empty() => println!("info string no endgame tables found in {}", value),
+                Ok(database) => self.database = Some(Arc::new(database)),
+                Err(err) => println!("info string couldn't load the endgame tables: {}", err),
+            },
+            "variant" => match Variant::from_name(&value) {
+                Some(variant) => {
+                    self.variant = variant;
+                    self.rules = Rules::new(variant);
+                    self.gameboard = GameBoard::with_variant(variant);
+                    if let Some(table) = self.table.as_mut() {
+                        table.clear();
+                    }
+                }
+                None => println!("info string unknown variant {}", value),
+            },
+            "rules" => match Rules::parse(&value, self.variant) {
+                Some(rules) => {
+                    self.rules = rules;
+                    self.gameboard = GameBoard::with_rules(self.variant, rules);
+                    if let Some(table) = self.table.as_mut() {
+                        table.clear();
+                    }
+                }
+                None => println!("info string unknown rules {}", value),
+            },
+            "weights" => match Weights::load(Path::new(&value)) {
+                Ok(weights) => {
+                    self.weights = weights;
+                    //scores of the old evaluation would mix with the new ones
+                    if let Some(table) = self.table.as_mut() {
+                        table.clear();
+                    }
+                }
+                Err(err) => println!("info string couldn't load the weights: {}", err),
+            },
+            _ => println!("info string unknown option {}", words.join(" ")),
+        }
+    }
+
+    fn start_search(&mut self, words: &[&str]) {
+        let mut limits = SearchLimits::default();
+        let mut clock: Option<Duration> = None;
+        let mut increment = Duration::ZERO;
+        let (own_time, own_increment) = match self.gameboard.turn {
+            Player::White => ("wtime", "winc"),
+            Player::Black => ("btime", "binc"),
+        };
+        let mut index = 0;
+        while index < words.len() {
+            let value = words.get(index + 1).and_then(|value| value.parse::<u64>().ok());
+            match (words[index], value) {
+                ("depth", Some(depth)) => limits.depth = Some(depth.min(MAX_DEPTH as u64) as u8),
+                ("nodes", Some(nodes)) => limits.nodes = Some(nodes),
+                ("movetime", Some(millis)) => limits.time = Some(Duration::from_millis(millis)),
+                (name, Some(millis)) if name == own_time => clock = Some(Duration::from_millis(millis)),
+                (name, Some(millis)) if name == own_increment => increment = Duration::from_millis(millis),
+                ("infinite", _) => {
+                    index += 1;
+                    continue;
+                }
+                (_, Some(_)) => {}
+                (other, None) => {
+                    println!("info string ignoring {}", other);
+                    index += 1;
+                    continue;
+                }
+            }
+            index += 2;
+        }
+        if let (None, Some(clock)) = (limits.time, clock) {
+            limits.time = Some(clock / MOVES_TO_GO as u32 + increment / 2);
+        }
+
+        self.search_limited = limits.depth.is_some() || limits.nodes.is_some() || limits.time.is_some();
+        self.stop = Arc::new(AtomicBool::new(false));
+        limits.stop = Some(self.stop.clone());
+        limits.database = self.database.clone();
+        limits.weights = self.weights;
+        let gameboard = self.gameboard.clone();
+        let mut table = self.table.take().unwrap_or_default();
+        let start = Instant::now();
+        self.search_thread = Some(thread::spawn(move || {
+            let result = search(&gameboard, gameboard.turn, limits, &mut table, |info| print_info(info, start.elapsed()));
+            match result {
+                Some(result) => println!("bestmove {}", result.millmove),
+                None => println!("bestmove none"),
+            }
+            table
+        }));
+    }
+
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait_for_search();
+    }
+
+    // a limited search runs to its end, an unlimited one is stopped
+    fn wait_for_search(&mut self) {
+        if !self.search_limited {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        if let Some(search_thread) = self.search_thread.take() {
+            self.table = search_thread.join().ok();
+        }
+    }
+}
+
+fn print_info(result: &SearchResult, elapsed: Duration) {
+    let score = match win_distance(result.score) {
+        Some(plies) => format!("mate {}", (plies + plies.signum()) / 2),
+        None => format!("cp {}", result.score),
+    };
+    let millis = elapsed.as_millis().max(1) as u64;
+    println!(
+        "info depth {} score {} nodes {} time {} nps {} pv {}",
+        result.depth, score, result.nodes, millis, result.nodes * 1000 / millis, result.millmove
+    );
+}
@@ -0,0 +1,390 @@
+use crate::datastructures::*;
+use crate::rules::*;
+use crate::variant::*;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// outcome of a recorded game, written like in PGN
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Unfinished,
+}
+
+impl GameResult {
+    pub fn from_winner(game_over: bool, winner: Option<Player>) -> GameResult {
+        match (game_over, winner) {
+            (_, Some(Player::White)) => GameResult::WhiteWins,
+            (_, Some(Player::Black)) => GameResult::BlackWins,
+            (true, None) => GameResult::Draw,
+            (false, None) => GameResult::Unfinished,
+        }
+    }
+
+    fn parse(token: &str) -> Option<GameResult> {
+        match token {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unfinished),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let token = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unfinished => "*",
+        };
+        write!(f, "{}", token)
+    }
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+    InvalidHeader { line: usize, content: String },
+    InvalidPosition(PositionError),
+    InvalidRules(String),
+    IllegalMove { number: usize, text: String },
+    MissingMoves,
+    StateMismatch,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::InvalidHeader { line, content } => write!(f, "invalid header in line {}: {}", line, content),
+            RecordError::InvalidPosition(err) => write!(f, "invalid start position: {}", err),
+            RecordError::InvalidRules(rules) => write!(f, "unknown rules: {}", rules),
+            RecordError::IllegalMove { number, text } => write!(f, "illegal move {} ({}. move of the game)", text, number),
+            RecordError::MissingMoves => write!(f, "the record has no move list"),
+            RecordError::StateMismatch => write!(f, "the saved position or turn counter doesn't match the moves of the game"),
+        }
+    }
+}
+
+// a whole game: headers like in PGN, the start position and all moves in standard notation
+//   [White "human"]
+//   [Black "engine"]
+//   [Result "1-0"]
+//
+//   1. d5 d6 2. e5 f6 3. c5xd6 ... 1-0
+// a start position other than the empty board is stored in the "FEN" header,
+// the "Variant" header tells the board of games starting on the empty board,
+// rules other than the usual ones of the variant are stored in the "Rules" header
+#[derive(Clone)]
+pub struct GameRecord {
+    headers: Vec<(String, String)>,
+    pub start: GameBoard,
+    pub moves: Vec<MillMove>,
+    pub result: GameResult,
+}
+
+impl GameRecord {
+    pub fn new(white: &str, black: &str, variant: Variant) -> GameRecord {
+        let mut record = GameRecord {
+            headers: Vec::new(),
+            start: GameBoard::with_variant(variant),
+            moves: Vec::new(),
+            result: GameResult::Unfinished,
+        };
+        record.set_header("Event", "Casual game");
+        record.set_header("Date", &today());
+        record.set_header("White", white);
+        record.set_header("Black", black);
+        record.set_header("Variant", &variant.to_string());
+        record
+    }
+
+    pub fn get_header(&self, key: &str) -> Option<&str> {
+        self.headers.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_header(&mut self, key: &str, value: &str) {
+        match self.headers.iter_mut().find(|(name, _)| name == key) {
+            Some(header) => header.1 = value.to_string(),
+            None => self.headers.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    // every position of the game together with the move leading to it, starting with the start position
+    pub fn replay(&self) -> Result<Vec<(Option<MillMove>, GameBoard)>, RecordError> {
+        let mut positions = vec![(None, self.start.clone())];
+        let mut gameboard = self.start.clone();
+        for (number, millmove) in self.moves.iter().enumerate() {
+            gameboard = gameboard.move_simulator(*millmove).map_err(|_| RecordError::IllegalMove { number: number + 1, text: millmove.to_string() })?;
+            positions.push((Some(*millmove), gameboard.clone()));
+        }
+        Ok(positions)
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (key, value) in &self.headers {
+            if key != "Result" && key != "FEN" && key != "Rules" {
+                writeln!(f, "[{} \"{}\"]", key, escape(value))?;
+            }
+        }
+        writeln!(f, "[Result \"{}\"]", self.result)?;
+        if self.start.to_position() != GameBoard::with_variant(self.start.variant).to_position() {
+            writeln!(f, "[FEN \"{}\"]", self.start.to_position())?;
+        }
+        if self.start.rules != Rules::new(self.start.variant) {
+            writeln!(f, "[Rules \"{}\"]", self.start.rules)?;
+        }
+        writeln!(f)?;
+        let mut line = String::new();
+        let mut gameboard = self.start.clone();
+        let mut number = 0;
+        let mut last_mover: Option<Player> = None;
+        for millmove in &self.moves {
+            //a new number starts with every move of white, or with the first move of the game
+            if last_mover.is_none() || (gameboard.turn == Player::White && last_mover != Some(Player::White)) {
+                number += 1;
+                let prefix = if gameboard.turn == Player::White { format!("{}.", number) } else { format!("{}...", number) };
+                push_token(f, &mut line, &prefix)?;
+            }
+            push_token(f, &mut line, &millmove.to_string())?;
+            last_mover = Some(gameboard.turn);
+            //an illegal move is still written, the numbering just goes on from the last legal position
+            if let Ok(next) = gameboard.move_simulator(*millmove) {
+                gameboard = next;
+            }
+        }
+        push_token(f, &mut line, &self.result.to_string())?;
+        writeln!(f, "{}", line)
+    }
+}
+
+// appends a token to the move text, lines are wrapped at 80 characters
+fn push_token(f: &mut fmt::Formatter, line: &mut String, token: &str) -> fmt::Result {
+    if !line.is_empty() && line.len() + token.len() + 1 > 80 {
+        writeln!(f, "{}", line)?;
+        line.clear();
+    }
+    if !line.is_empty() {
+        line.push(' ');
+    }
+    line.push_str(token);
+    Ok(())
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn unescape(value: &str) -> String {
+    let mut output = String::new();
+    let mut chars = value.chars();
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' => output.extend(chars.next()),
+            _ => output.push(character),
+        }
+    }
+    output
+}
+
+impl FromStr for GameRecord {
+    type Err = RecordError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut record = GameRecord::new("?", "?", Variant::Nine);
+        record.headers.clear();
+        let mut movetext = String::new();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                let (key, value) = parse_header(line).ok_or_else(|| RecordError::InvalidHeader { line: number + 1, content: line.to_string() })?;
+                record.set_header(&key, &value);
+            } else if !line.starts_with('%') {
+                //a ';' comments out the rest of the line
+                movetext.push_str(line.split(';').next().unwrap_or(""));
+                movetext.push(' ');
+            }
+        }
+        if let Some(name) = record.get_header("Variant") {
+            let variant = Variant::from_name(name).ok_or_else(|| RecordError::InvalidPosition(PositionError::UnknownVariant(name.to_string())))?;
+            record.start = GameBoard::with_variant(variant);
+        }
+        if let Some(position) = record.get_header("FEN") {
+            record.start = position.parse::<GameBoard>().map_err(RecordError::InvalidPosition)?;
+        }
+        if let Some(text) = record.get_header("Rules") {
+            record.start.rules = Rules::parse(text, record.start.variant).ok_or_else(|| RecordError::InvalidRules(text.to_string()))?;
+        }
+        if let Some(result) = record.get_header("Result").and_then(GameResult::parse) {
+            record.result = result;
+        }
+        let movetext = strip_comments(&movetext);
+        if movetext.trim().is_empty() {
+            return Err(RecordError::MissingMoves)
+        }
+        let mut gameboard = record.start.clone();
+        for token in movetext.split_whitespace() {
+            if let Some(result) = GameResult::parse(token) {
+                record.result = result;
+                break;
+            }
+            //move numbers like "12." or "12..." also in front of a move "12.d5"
+            let token = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+            if token.is_empty() {
+                continue;
+            }
+            let illegal = || RecordError::IllegalMove { number: record.moves.len() + 1, text: token.to_string() };
+            let millmove = MillMove::from_notation(token, &gameboard).ok_or_else(illegal)?;
+            millmove.validate_complete(&gameboard).map_err(|_| illegal())?;
+            gameboard = gameboard.move_simulator(millmove).map_err(|_| illegal())?;
+            record.moves.push(millmove);
+        }
+        Ok(record)
+    }
+}
+
+// reads [Key "Value"]
+fn parse_header(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (key, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((key.to_string(), unescape(value)))
+}
+
+// removes {comments} from the move text
+fn strip_comments(movetext: &str) -> String {
+    let mut output = String::new();
+    let mut depth = 0;
+    for character in movetext.chars() {
+        match character {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            _ if depth == 0 => output.push(character),
+            _ => {}
+        }
+    }
+    output
+}
+
+// reads all games of a file containing several records one after another
+pub fn parse_records(text: &str) -> Result<Vec<GameRecord>, RecordError> {
+    let mut records = Vec::new();
+    let mut current = String::new();
+    let mut in_moves = false;
+    for line in text.lines() {
+        //a header after the move list starts the next game
+        if line.trim_start().starts_with('[') && in_moves {
+            records.push(current.parse::<GameRecord>()?);
+            current.clear();
+            in_moves = false;
+        }
+        if !line.trim().is_empty() && !line.trim_start().starts_with('[') {
+            in_moves = true;
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.trim().is_empty() {
+        records.push(current.parse::<GameRecord>()?);
+    }
+    Ok(records)
+}
+
+// current date as "YYYY.MM.DD"
+fn today() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+    let days = (seconds / 86_400) as i64;
+    //civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    // a record of random moves from the start position
+    fn random_record(start: GameBoard, moves: usize, seed: u64) -> GameRecord {
+        let mut record = GameRecord::new("engine", "say \"hi\" \\ human", start.variant);
+        record.start = start.clone();
+        let mut rng = Rng::new(seed);
+        let mut gameboard = start;
+        for _ in 0..moves {
+            let turns = gameboard.possible_turns(gameboard.turn);
+            if turns.is_empty() {
+                break;
+            }
+            let millmove = turns[rng.below(turns.len())];
+            //a player with less than three stones has lost, nothing can be taken anymore
+            let Ok(next) = gameboard.move_simulator(millmove) else { break };
+            gameboard = next;
+            record.moves.push(millmove);
+        }
+        record
+    }
+
+    fn assert_round_trip(record: &GameRecord) {
+        let text = record.to_string();
+        let parsed: GameRecord = text.parse().unwrap();
+        assert_eq!(parsed.to_string(), text);
+        assert_eq!(parsed.start.to_position(), record.start.to_position());
+        assert_eq!(parsed.start.rules, record.start.rules);
+        assert_eq!(parsed.result, record.result);
+        assert_eq!(parsed.get_header("Black"), record.get_header("Black"));
+        let names = |record: &GameRecord| record.moves.iter().map(|millmove| millmove.to_string()).collect::<Vec<String>>();
+        assert_eq!(names(&parsed), names(record));
+    }
+
+    #[test]
+    fn records_round_trip() {
+        for (seed, variant) in Variant::ALL.into_iter().enumerate() {
+            let mut record = random_record(GameBoard::with_variant(variant), 60, seed as u64);
+            assert!(record.moves.len() >= 10, "{} ended after {} moves", variant, record.moves.len());
+            record.result = GameResult::Draw;
+            assert_round_trip(&record);
+        }
+    }
+
+    #[test]
+    fn records_with_rules_and_start_position_round_trip() {
+        let rules = Rules::parse("lasker,double-removal,blocked-draw", Variant::Nine).unwrap();
+        let record = random_record(GameBoard::with_rules(Variant::Nine, rules), 80, 7);
+        assert!(record.to_string().contains("[Rules "));
+        assert_round_trip(&record);
+
+        let start: GameBoard = "WWWWEEEE/BBBBEEEE/EEEEEEEE w m 0 0 1".parse().unwrap();
+        let mut record = random_record(start, 30, 11);
+        record.result = GameResult::WhiteWins;
+        assert!(record.to_string().contains("[FEN "));
+        assert_round_trip(&record);
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected() {
+        let record = "[Result \"*\"]\n\n1. d5 d5 *".parse::<GameRecord>();
+        assert!(matches!(record, Err(RecordError::IllegalMove { number: 2, .. })));
+        assert!(matches!("[Result \"*\"]\n".parse::<GameRecord>(), Err(RecordError::MissingMoves)));
+    }
+}
@@ -0,0 +1,74 @@
+use crate::variant::*;
+use std::fmt;
+
+// what happens to a player who can't move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockedOutcome {
+    Loss,
+    Draw,
+}
+
+// house rules of a game, every variant has its usual rules as default
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rules {
+    // stones of a player with three stones left may jump to any free field
+    pub flying: bool,
+    // stones may be moved before all stones are placed, every turn either places or moves
+    pub lasker: bool,
+    // closing two mills with one move takes two stones
+    pub double_removal: bool,
+    // stones in mills may always be taken, not only if all stones are in mills
+    pub take_from_mills: bool,
+    pub blocked: BlockedOutcome,
+}
+
+impl Rules {
+    pub fn new(variant: Variant) -> Rules {
+        Rules {
+            flying: matches!(variant, Variant::Nine | Variant::Twelve),
+            lasker: false,
+            double_removal: false,
+            take_from_mills: false,
+            blocked: BlockedOutcome::Loss,
+        }
+    }
+
+    // reads a comma separated list of switches like "lasker,no-flying,blocked-draw",
+    // switches that aren't given keep the default of the variant. None if a switch is unknown
+    pub fn parse(text: &str, variant: Variant) -> Option<Rules> {
+        let mut rules = Rules::new(variant);
+        for switch in text.split(',').map(str::trim).filter(|switch| !switch.is_empty()) {
+            match switch.to_ascii_lowercase().as_str() {
+                "flying" => rules.flying = true,
+                "no-flying" => rules.flying = false,
+                "lasker" => rules.lasker = true,
+                "double-removal" => rules.double_removal = true,
+                "take-from-mills" => rules.take_from_mills = true,
+                "blocked-draw" => rules.blocked = BlockedOutcome::Draw,
+                "blocked-loss" => rules.blocked = BlockedOutcome::Loss,
+                _ => return None,
+            }
+        }
+        Some(rules)
+    }
+}
+
+// writes the switches read by Rules::parse, flying is always written since its default depends on the variant
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut switches = vec![if self.flying { "flying" } else { "no-flying" }];
+        if self.lasker {
+            switches.push("lasker");
+        }
+        if self.double_removal {
+            switches.push("double-removal");
+        }
+        if self.take_from_mills {
+            switches.push("take-from-mills");
+        }
+        if self.blocked == BlockedOutcome::Draw {
+            switches.push("blocked-draw");
+        }
+        write!(f, "{}", switches.join(","))
+    }
+}
@@ -0,0 +1,294 @@
+use crate::datastructures::*;
+use crate::endgame::*;
+use crate::evaluation::*;
+use crate::rules::BlockedOutcome;
+use crate::transposition::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// scores above this are forced wins, the distance to the win is subtracted
+pub const WIN_SCORE: i32 = 100_000;
+pub const MAX_DEPTH: u8 = 64;
+// wins from the endgame database can be further away than the search depth
+const MAX_WIN_DISTANCE: i32 = 512;
+
+// limits of one search; without any limit the search deepens until it is stopped
+#[derive(Clone, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+    pub stop: Option<Arc<AtomicBool>>,
+    // solved positions are looked up instead of searched
+    pub database: Option<Arc<EndgameDatabase>>,
+    // the weights of the evaluation of the positions at the end of the search
+    pub weights: Weights,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u8) -> SearchLimits {
+        SearchLimits { depth: Some(depth), ..SearchLimits::default() }
+    }
+}
+
+// best move found by a search, the score is seen from the side to move
+#[derive(Clone, Copy)]
+pub struct SearchResult {
+    pub millmove: MillMove,
+    pub score: i32,
+    pub depth: u8,
+    pub nodes: u64,
+}
+
+struct Searcher<'a> {
+    limits: SearchLimits,
+    table: &'a mut TranspositionTable,
+    start: Instant,
+    nodes: u64,
+    aborted: bool,
+    // hashes of the positions on the way from the root to the current node
+    path: Vec<u64>,
+}
+
+// plies until the game is decided for a winning (positive) or losing (negative) score
+pub fn win_distance(score: i32) -> Option<i32> {
+    if score > WIN_SCORE - MAX_WIN_DISTANCE {
+        Some(WIN_SCORE - score)
+    } else if score < -WIN_SCORE + MAX_WIN_DISTANCE {
+        Some(-WIN_SCORE - score)
+    } else {
+        None
+    }
+}
+
+// searches the best move of the player with iterative deepening, None if the player can't move.
+// the results stay in the table, so the caller keeps one table for all its searches
+pub fn find_best_move(board: &GameBoard, player: Player, limits: SearchLimits, table: &mut TranspositionTable) -> Option<SearchResult> {
+    search(board, player, limits, table, |_| {})
+}
+
+// like find_best_move, but reports the result of every finished iteration
+pub fn search(board: &GameBoard, player: Player, limits: SearchLimits, table: &mut TranspositionTable, mut report: impl FnMut(&SearchResult)) -> Option<SearchResult> {
+    let mut root = board.clone();
+    root.turn = player;
+    let board = &root;
+    let mut root_turns = generate_turns(board);
+    if root_turns.is_empty() || terminal_score(board, 0).is_some() {
+        return None
+    }
+    let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+    table.new_search();
+    let root_hash = board.hash();
+    if let Some(known) = table.probe(root_hash, 0).and_then(|entry| entry.best_move) {
+        move_to_front(&mut root_turns, &known);
+    }
+    let mut searcher = Searcher { limits, table, start: Instant::now(), nodes: 0, aborted: false, path: vec![root_hash] };
+    let mut best: Option<SearchResult> = None;
+
+    for depth in 1..=max_depth {
+        let mut alpha = -WIN_SCORE - 1;
+        let mut iteration_best: Option<(MillMove, i32)> = None;
+        for turn in root_turns.iter() {
+            let Ok(next) = board.move_simulator(*turn) else { continue };
+            let score = searcher.child_score(board, &next, depth - 1, 1, alpha, WIN_SCORE + 1);
+            if searcher.aborted {
+                break;
+            }
+            if iteration_best.is_none() || score > alpha {
+                alpha = score;
+                iteration_best = Some((*turn, score));
+            }
+        }
+        //an unfinished iteration is only used if nothing better is known
+        if let Some((millmove, score)) = iteration_best {
+            if !searcher.aborted || best.is_none() {
+                best = Some(SearchResult { millmove, score, depth, nodes: searcher.nodes });
+            }
+        }
+        if searcher.aborted {
+            break;
+        }
+        if let Some(result) = &best {
+            searcher.table.store(root_hash, 0, depth, result.score, Bound::Exact, Some(result.millmove));
+            report(result);
+        }
+        //search the best move of this iteration first in the next one
+        if let Some(result) = best {
+            move_to_front(&mut root_turns, &result.millmove);
+            if win_distance(result.score).is_some() {
+                break;
+            }
+        }
+    }
+    //stopped before the first move was searched, any legal move is better than none
+    let fallback = SearchResult { millmove: root_turns[0], score: 0, depth: 0, nodes: 0 };
+    Some(SearchResult { nodes: searcher.nodes, ..best.unwrap_or(fallback) })
+}
+
+impl Searcher<'_> {
+    fn should_stop(&self) -> bool {
+        if let Some(max_nodes) = self.limits.nodes {
+            if self.nodes > max_nodes {
+                return true
+            }
+        }
+        //the clock and the stop flag are only looked at every few nodes
+        if !self.nodes.is_multiple_of(1024) {
+            return false
+        }
+        let timed_out = self.limits.time.is_some_and(|time| self.start.elapsed() >= time);
+        let stopped = self.limits.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed));
+        timed_out || stopped
+    }
+
+    // score of the child position from the view of the side to move on the parent board
+    fn child_score(&mut self, board: &GameBoard, next: &GameBoard, depth: u8, ply: u8, alpha: i32, beta: i32) -> i32 {
+        //a pending removal keeps the same player to move
+        if next.turn == board.turn {
+            self.negamax(next, depth, ply, alpha, beta)
+        } else {
+            -self.negamax(next, depth, ply, -beta, -alpha)
+        }
+    }
+
+    fn negamax(&mut self, board: &GameBoard, depth: u8, ply: u8, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            self.aborted = true;
+            return 0
+        }
+        if let Some(score) = terminal_score(board, ply) {
+            return score
+        }
+        //going back to a position of the current line can't be better than a draw
+        let hash = board.hash();
+        if self.path.contains(&hash) {
+            return 0
+        }
+        if let Some(value) = self.limits.database.as_ref().and_then(|database| database.probe(board)) {
+            return match value {
+                EndgameValue::Win(plies) => WIN_SCORE - ply as i32 - plies as i32,
+                EndgameValue::Loss(plies) => -WIN_SCORE + ply as i32 + plies as i32,
+                EndgameValue::Draw => 0,
+            }
+        }
+        if depth == 0 {
+            return evaluate(board, board.turn, &self.limits.weights)
+        }
+        let known = self.table.probe(hash, ply);
+        if let Some(entry) = known.filter(|entry| entry.depth >= depth) {
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if usable {
+                return entry.score
+            }
+        }
+        let mut turns = generate_turns(board);
+        if turns.is_empty() {
+            return -WIN_SCORE + ply as i32
+        }
+        //the best move of an earlier search of this position is tried first
+        if let Some(known_move) = known.and_then(|entry| entry.best_move) {
+            move_to_front(&mut turns, &known_move);
+        }
+        let original_alpha = alpha;
+        let mut best = -WIN_SCORE - 1;
+        let mut best_move = None;
+        self.path.push(hash);
+        for turn in turns {
+            let Ok(next) = board.move_simulator(turn) else { continue };
+            let score = self.child_score(board, &next, depth - 1, ply + 1, alpha, beta);
+            if self.aborted {
+                self.path.pop();
+                return 0
+            }
+            if score > best {
+                best = score;
+                best_move = Some(turn);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        self.path.pop();
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        //a move that failed low is no better than the others
+        let best_move = if bound == Bound::Upper { None } else { best_move };
+        self.table.store(hash, ply, depth, best, bound, best_move);
+        best
+    }
+}
+
+fn same_turn(a: &MillMove, b: &MillMove) -> bool {
+    a.origin == b.origin && a.destination == b.destination && a.remove == b.remove
+}
+
+fn move_to_front(turns: &mut Vec<MillMove>, millmove: &MillMove) {
+    if let Some(position) = turns.iter().position(|turn| same_turn(turn, millmove)) {
+        let first = turns.remove(position);
+        turns.insert(0, first);
+    }
+}
+
+fn generate_turns(board: &GameBoard) -> Vec<MillMove> {
+    let mut turns = board.possible_turns(board.turn);
+    //captures are searched first, they cause most of the cutoffs
+    turns.sort_by_key(|turn| turn.remove.is_none());
+    turns
+}
+
+// score of a finished game from the view of the player to move, None if the game goes on
+pub(crate) fn terminal_score(board: &GameBoard, ply: u8) -> Option<i32> {
+    let player = board.turn;
+    //a player needs at least three stones on the board and in hand
+    let lost = |color: Player| board.get_stones_in_hand(color) + board.get_stones_of(color) < 3;
+    if lost(player) {
+        return Some(-WIN_SCORE + ply as i32)
+    }
+    if lost(get_other_player(player)) {
+        return Some(WIN_SCORE - ply as i32)
+    }
+    if board.is_full() {
+        return Some(0)
+    }
+    if board.pending_removals == 0 && board.is_blocked(player) {
+        return match board.rules.blocked {
+            BlockedOutcome::Loss => Some(-WIN_SCORE + ply as i32),
+            BlockedOutcome::Draw => Some(0),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Rules;
+    use crate::variant::Variant;
+
+    #[test]
+    fn full_board_is_a_draw_and_blockade_a_loss() {
+        //the 24 stones of Twelve Men's Morris fill the board
+        let full = GameBoard::from_position("WBWBWBWB/BWBWBWBW/WBWBWBWB w m 0 0 0 twelve").unwrap();
+        assert_eq!(terminal_score(&full, 3), Some(0));
+        //the only free field is surrounded by black stones
+        let mut blocked = GameBoard::from_position("EBWWWWWB/BWWWWWWW/BBBBBBBB w m 0 0 0 twelve").unwrap();
+        assert_eq!(blocked.rules, Rules::new(Variant::Twelve));
+        assert_eq!(terminal_score(&blocked, 3), Some(-WIN_SCORE + 3));
+        blocked.rules.blocked = BlockedOutcome::Draw;
+        assert_eq!(terminal_score(&blocked, 3), Some(0));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::datastructures::*;
+use crate::variant::*;
+
+// the board looks the same after turning it by 90 degrees, mirroring it
+// and swapping the inner with the outer ring, together 4 * 2 * 2 symmetries.
+// the diagonals of Twelve Men's Morris and the centre of Three Men's Morris keep all of them,
+// on boards with a single ring the swap leaves every field where it is
+pub const SYMMETRY_COUNT: usize = 16;
+
+// the field every field is moved to by every symmetry on the board of every variant, slot 0 is unused
+const FIELD_MAPS: [[[u8; 25]; SYMMETRY_COUNT]; 4] = build_field_maps();
+// the symmetry undoing every symmetry, the same for all variants
+const INVERSES: [usize; SYMMETRY_COUNT] = build_inverses();
+// the masks of the three bytes of a field mask after every symmetry, used to transform whole masks quickly
+static MASK_MAPS: [[[[u32; 256]; 3]; SYMMETRY_COUNT]; 4] = build_mask_maps();
+
+// symmetry number s turns the board by s % 4 quarters, mirrors it if s & 4 is set
+// and swaps the inner and outer ring if s & 8 is set, 0 leaves the board unchanged
+const fn map_field(variant: Variant, symmetry: usize, index: u8) -> u8 {
+    let rings = variant.topology().rings;
+    //the centre stays where it is
+    if index > rings * 8 {
+        return index
+    }
+    let ring = (index - 1) / 8;
+    let mut position = (index - 1) % 8;
+    if symmetry & 4 != 0 {
+        position = (8 - position) % 8;
+    }
+    position = (position + 2 * (symmetry % 4) as u8) % 8;
+    let ring = if symmetry & 8 != 0 { rings - 1 - ring } else { ring };
+    ring * 8 + position + 1
+}
+
+const fn build_field_maps() -> [[[u8; 25]; SYMMETRY_COUNT]; 4] {
+    let mut maps = [[[0u8; 25]; SYMMETRY_COUNT]; 4];
+    let mut variant = 0;
+    while variant < Variant::ALL.len() {
+        let mut symmetry = 0;
+        while symmetry < SYMMETRY_COUNT {
+            let mut index: u8 = 1;
+            while index <= Variant::ALL[variant].topology().field_count {
+                maps[variant][symmetry][index as usize] = map_field(Variant::ALL[variant], symmetry, index);
+                index += 1;
+            }
+            symmetry += 1;
+        }
+        variant += 1;
+    }
+    maps
+}
+
+const fn build_inverses() -> [usize; SYMMETRY_COUNT] {
+    let mut inverses = [0; SYMMETRY_COUNT];
+    let mut symmetry = 0;
+    while symmetry < SYMMETRY_COUNT {
+        let mut candidate = 0;
+        while candidate < SYMMETRY_COUNT {
+            let mut undoes = true;
+            let mut index: u8 = 1;
+            while index <= Variant::Nine.topology().field_count {
+                if map_field(Variant::Nine, candidate, map_field(Variant::Nine, symmetry, index)) != index {
+                    undoes = false;
+                }
+                index += 1;
+            }
+            if undoes {
+                inverses[symmetry] = candidate;
+            }
+            candidate += 1;
+        }
+        symmetry += 1;
+    }
+    inverses
+}
+
+const fn build_mask_maps() -> [[[[u32; 256]; 3]; SYMMETRY_COUNT]; 4] {
+    let mut maps = [[[[0u32; 256]; 3]; SYMMETRY_COUNT]; 4];
+    let mut variant = 0;
+    while variant < Variant::ALL.len() {
+        let field_count = Variant::ALL[variant].topology().field_count;
+        let mut symmetry = 0;
+        while symmetry < SYMMETRY_COUNT {
+            let mut part = 0;
+            while part < 3 {
+                let mut byte = 0;
+                while byte < 256 {
+                    let mut mask = 0u32;
+                    let mut bit = 0;
+                    while bit < 8 {
+                        let index = (part * 8 + bit + 1) as u8;
+                        if byte & (1 << bit) != 0 && index <= field_count {
+                            mask |= field_bit(map_field(Variant::ALL[variant], symmetry, index));
+                        }
+                        bit += 1;
+                    }
+                    maps[variant][symmetry][part][byte] = mask;
+                    byte += 1;
+                }
+                part += 1;
+            }
+            symmetry += 1;
+        }
+        variant += 1;
+    }
+    maps
+}
+
+// the mask of all fields of the given mask after the symmetry on the board of the variant
+pub fn transform_mask(variant: Variant, symmetry: usize, mask: u32) -> u32 {
+    let maps = &MASK_MAPS[variant as usize][symmetry];
+    maps[0][(mask & 0xFF) as usize] | maps[1][((mask >> 8) & 0xFF) as usize] | maps[2][((mask >> 16) & 0xFF) as usize]
+}
+
+// the field the symmetry moves the given field to on the board of the variant
+pub fn transform_field(variant: Variant, symmetry: usize, index: u8) -> u8 {
+    FIELD_MAPS[variant as usize][symmetry][index as usize]
+}
+
+// the symmetry that moves every field back to where it was before the given one
+pub fn inverse(symmetry: usize) -> usize {
+    INVERSES[symmetry]
+}
+
+impl GameBoard {
+    // the same position with all stones moved by the symmetry, counters, phase and turn stay the same
+    pub fn transformed(&self, symmetry: usize) -> GameBoard {
+        let transform = |color: Player| transform_mask(self.variant, symmetry, self.get_stone_mask(color));
+        self.with_stone_masks(transform(Player::White), transform(Player::Black))
+    }
+
+    // the symmetry that turns the position into its canonical representative:
+    // of all 16 symmetric positions the one with the smallest white and then black stone mask
+    pub fn canonical_symmetry(&self) -> usize {
+        let key = |symmetry: usize| (transform_mask(self.variant, symmetry, self.get_stone_mask(Player::White)), transform_mask(self.variant, symmetry, self.get_stone_mask(Player::Black)));
+        (0..SYMMETRY_COUNT).min_by_key(|symmetry| key(*symmetry)).unwrap_or(0)
+    }
+
+    // the canonical representative of the position together with the symmetry leading to it,
+    // moves of this position are turned into moves of the representative with MillMove::transformed
+    // and back with the inverse symmetry
+    pub fn canonical(&self) -> (GameBoard, usize) {
+        let symmetry = self.canonical_symmetry();
+        (self.transformed(symmetry), symmetry)
+    }
+}
+
+impl MillMove {
+    // the same move on the board transformed by the symmetry, 0 stays 0 for placements and removals
+    pub fn transformed(&self, symmetry: usize) -> MillMove {
+        let field = |index: u8| if index == 0 { 0 } else { transform_field(self.variant, symmetry, index) };
+        MillMove {
+            origin: field(self.origin),
+            destination: field(self.destination),
+            remove: self.remove.map(field),
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    #[test]
+    fn symmetries_keep_lines_and_mills() {
+        for variant in Variant::ALL {
+            let topology = variant.topology();
+            let mills = &topology.mills[..topology.mill_count];
+            for symmetry in 0..SYMMETRY_COUNT {
+                assert_eq!(transform_mask(variant, symmetry, topology.all_fields), topology.all_fields);
+                for field in 1..=topology.field_count {
+                    let moved = transform_field(variant, symmetry, field);
+                    assert_eq!(transform_mask(variant, symmetry, field_bit(field)), field_bit(moved));
+                    assert_eq!(transform_mask(variant, symmetry, topology.neighbours[field as usize]), topology.neighbours[moved as usize], "{} {}", variant, symmetry);
+                    assert_eq!(transform_field(variant, inverse(symmetry), moved), field);
+                }
+                for mill in mills {
+                    assert!(mills.contains(&transform_mask(variant, symmetry, *mill)), "{} {}", variant, symmetry);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn symmetric_positions_share_canonical_form_and_moves() {
+        let mut rng = Rng::new(14);
+        for variant in Variant::ALL {
+            let mut gameboard = GameBoard::with_variant(variant);
+            for _ in 0..40 {
+                let turns = gameboard.possible_turns(gameboard.turn);
+                let (canonical, _) = gameboard.canonical();
+                for symmetry in 0..SYMMETRY_COUNT {
+                    let transformed = gameboard.transformed(symmetry);
+                    let (again, _) = transformed.canonical();
+                    assert_eq!(again.to_position(), canonical.to_position());
+                    assert_eq!(again.hash(), canonical.hash());
+                    assert_eq!(transformed.possible_turns(transformed.turn).len(), turns.len());
+                    for millmove in &turns {
+                        let moved = millmove.transformed(symmetry);
+                        assert_eq!(moved.validate_complete(&transformed), Ok(()), "{} {} {}", gameboard.to_position(), symmetry, millmove);
+                        //taking the third last stone ends the game, both boards have to agree on that as well
+                        let after = transformed.move_simulator(moved).map(|after| after.to_position());
+                        assert_eq!(after, gameboard.move_simulator(*millmove).map(|after| after.transformed(symmetry).to_position()));
+                        assert_eq!(moved.transformed(inverse(symmetry)).to_string(), millmove.to_string());
+                    }
+                }
+                if turns.is_empty() {
+                    break;
+                }
+                let Ok(next) = gameboard.move_simulator(turns[rng.below(turns.len())]) else { break };
+                gameboard = next;
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::datastructures::*;
+use crate::search::*;
+use std::mem;
+
+pub const DEFAULT_TABLE_MB: usize = 16;
+
+// what the stored score says about the real score of the position
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    Exact,
+    // the real score is at least the stored one (the search failed high)
+    Lower,
+    // the real score is at most the stored one (the search failed low)
+    Upper,
+}
+
+#[derive(Clone, Copy)]
+pub struct Entry {
+    key: u64,
+    pub best_move: Option<MillMove>,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    generation: u8,
+}
+
+// the first slot keeps the deepest result, the second always the latest one.
+// mill positions come up again and again in the move phase, so the deep results
+// of earlier searches stay useful and are only given up once they're outdated
+#[derive(Clone, Copy, Default)]
+struct Bucket {
+    deep: Option<Entry>,
+    recent: Option<Entry>,
+}
+
+// fixed-size hash table of search results keyed by the zobrist hash of the position
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    generation: u8,
+    // the buckets filled since the last clear, as long as there are few of them clear only resets these.
+    // short searches of many positions don't have to wipe the whole table every time
+    filled: Vec<usize>,
+}
+
+impl TranspositionTable {
+    // a table using at most the given number of megabytes
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let wanted = (size_mb.max(1) << 20) / mem::size_of::<Bucket>();
+        //a power of two lets the hash be masked instead of divided
+        let count = if wanted.is_power_of_two() { wanted } else { wanted.next_power_of_two() / 2 };
+        TranspositionTable { buckets: vec![Bucket::default(); count], generation: 0, filled: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        if self.filled.len() > self.filled_limit() {
+            self.buckets.iter_mut().for_each(|bucket| *bucket = Bucket::default());
+        } else {
+            for index in self.filled.drain(..) {
+                self.buckets[index] = Bucket::default();
+            }
+        }
+        self.filled.clear();
+        self.generation = 0;
+    }
+
+    // beyond this many filled buckets the whole table is reset
+    fn filled_limit(&self) -> usize {
+        self.buckets.len() / 16
+    }
+
+    // marks the entries of earlier searches as outdated
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn bucket_index(&self, key: u64) -> usize {
+        (key as usize) & (self.buckets.len() - 1)
+    }
+
+    // the stored result of the position, mate scores are converted to be relative to the given ply
+    pub fn probe(&self, key: u64, ply: u8) -> Option<Entry> {
+        let bucket = &self.buckets[self.bucket_index(key)];
+        let entry = [bucket.deep, bucket.recent].into_iter().flatten().find(|entry| entry.key == key)?;
+        Some(Entry { score: score_from_table(entry.score, ply), ..entry })
+    }
+
+    pub fn store(&mut self, key: u64, ply: u8, depth: u8, score: i32, bound: Bound, best_move: Option<MillMove>) {
+        let generation = self.generation;
+        let index = self.bucket_index(key);
+        let empty = self.buckets[index].deep.is_none() && self.buckets[index].recent.is_none();
+        if empty && self.filled.len() <= self.filled_limit() {
+            self.filled.push(index);
+        }
+        let bucket = &mut self.buckets[index];
+        let mut entry = Entry { key, best_move, score: score_to_table(score, ply), depth, bound, generation };
+        //a result without a move keeps the move known from an earlier search of the position
+        if entry.best_move.is_none() {
+            entry.best_move = [bucket.deep, bucket.recent].into_iter().flatten().find(|old| old.key == key).and_then(|old| old.best_move);
+        }
+        let replace_deep = match bucket.deep {
+            None => true,
+            Some(old) => old.key == key || old.generation != generation || depth >= old.depth,
+        };
+        if replace_deep {
+            //the entry pushed out of the deep slot is still a recent result
+            if let Some(old) = bucket.deep.filter(|old| old.key != key) {
+                bucket.recent = Some(old);
+            } else if bucket.recent.is_some_and(|old| old.key == key) {
+                bucket.recent = None;
+            }
+            bucket.deep = Some(entry);
+        } else {
+            bucket.recent = Some(entry);
+        }
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_TABLE_MB)
+    }
+}
+
+// mate scores are stored as distance from the stored position instead of from the root
+fn score_to_table(score: i32, ply: u8) -> i32 {
+    match win_distance(score) {
+        Some(_) if score > 0 => score + ply as i32,
+        Some(_) => score - ply as i32,
+        None => score,
+    }
+}
+
+fn score_from_table(score: i32, ply: u8) -> i32 {
+    match win_distance(score) {
+        Some(_) if score > 0 => score - ply as i32,
+        Some(_) => score + ply as i32,
+        None => score,
+    }
+}
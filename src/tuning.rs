@@ -0,0 +1,276 @@
+use crate::agent::*;
+use crate::datastructures::*;
+use crate::evaluation::*;
+use crate::millgame::MillGame;
+use crate::record::*;
+use crate::rng::Rng;
+use crate::rules::Rules;
+use crate::search::SearchLimits;
+use crate::variant::Variant;
+
+// Texel tuning of the evaluation weights: the engine plays games against itself, every position of a game
+// is labeled with its result and the weights are fitted so that a logistic function of the evaluation
+// predicts these results as good as possible. A match between the old and the new weights tells the Elo change
+#[derive(Clone, Copy, Debug)]
+pub struct TuningOptions {
+    pub variant: Variant,
+    pub rules: Rules,
+    // self-play games generating the positions
+    pub games: u32,
+    // search depth of the engines in all games
+    pub depth: u8,
+    // moves every player makes at random at the start of a game, so that the games differ
+    pub random_moves: u32,
+    // games still running after this many turns count as draws
+    pub max_turns: u32,
+    // steps of the gradient descent
+    pub iterations: u32,
+    // games of the match between the old and the tuned weights, half of them with each color
+    pub match_games: u32,
+    pub seed: u64,
+}
+
+impl Default for TuningOptions {
+    fn default() -> Self {
+        TuningOptions {
+            variant: Variant::Nine,
+            rules: Rules::new(Variant::Nine),
+            games: 500,
+            depth: 3,
+            random_moves: 3,
+            max_turns: 200,
+            iterations: 1000,
+            match_games: 200,
+            seed: 1,
+        }
+    }
+}
+
+// a position of a game reduced to the phase, the difference of the features of white and black and the result for white
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    pub placing: bool,
+    pub features: [f64; TERM_COUNT],
+    // 1 if white won the game, 0.5 for a draw and 0 if black won
+    pub result: f64,
+}
+
+impl Sample {
+    fn prediction(&self, weights: &[f64; 2 * TERM_COUNT], scale: f64) -> f64 {
+        let offset = if self.placing { 0 } else { TERM_COUNT };
+        let evaluation: f64 = self.features.iter().zip(&weights[offset..offset + TERM_COUNT]).map(|(feature, weight)| feature * weight).sum();
+        sigmoid(evaluation / scale)
+    }
+}
+
+// results of the games between two sets of weights, seen from the first one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl MatchResult {
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    // points per game, a draw is worth half a win
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    // the Elo difference that makes the score the expected one, the score is kept off 0 and 1 to stay finite
+    pub fn elo(&self) -> f64 {
+        let margin = 0.5 / self.games().max(1) as f64;
+        let score = self.score().clamp(margin, 1.0 - margin);
+        -400.0 * (1.0 / score - 1.0).log10()
+    }
+}
+
+// the engine with its evaluation weights, playing some random moves first
+struct SelfPlayAgent {
+    engine: EngineAgent,
+    rng: Rng,
+    random_moves: u32,
+}
+
+impl Agent for SelfPlayAgent {
+    fn name(&self) -> String {
+        String::from("engine")
+    }
+
+    fn choose_move(&mut self, gameboard: &GameBoard) -> Action {
+        if self.random_moves == 0 {
+            return self.engine.choose_move(gameboard)
+        }
+        self.random_moves -= 1;
+        let turns = gameboard.possible_turns(gameboard.turn);
+        if turns.is_empty() {
+            return Action::GiveUp
+        }
+        Action::Play(turns[self.rng.below(turns.len())])
+    }
+}
+
+fn self_play_agent(weights: &Weights, options: &TuningOptions, seed: u64) -> Box<dyn Agent> {
+    Box::new(SelfPlayAgent {
+        engine: EngineAgent::new(SearchLimits { weights: *weights, ..SearchLimits::depth(options.depth) }),
+        rng: Rng::new(seed),
+        random_moves: options.random_moves,
+    })
+}
+
+// plays one game between the weights, games with the same seed start with the same random moves
+fn play_game(white: &Weights, black: &Weights, options: &TuningOptions, seed: u64) -> MillGame {
+    let mut game = MillGame::with_rules(options.variant, options.rules, self_play_agent(white, options, seed), self_play_agent(black, options, seed ^ 0xB1AC));
+    game.play_turns(options.max_turns);
+    game
+}
+
+// plays the self-play games with the weights, the progress is reported with the number of finished games
+pub fn generate_games(weights: &Weights, options: &TuningOptions, mut progress: impl FnMut(u32)) -> Vec<GameRecord> {
+    let mut seeds = Rng::new(options.seed);
+    (1..=options.games)
+        .map(|game| {
+            let record = play_game(weights, weights, options, seeds.next_u64()).to_record();
+            progress(game);
+            record
+        })
+        .collect()
+}
+
+// every position of the games after the random moves, positions waiting for a removal are left out
+pub fn collect_samples(records: &[GameRecord], options: &TuningOptions) -> Vec<Sample> {
+    let mut samples = Vec::new();
+    for record in records {
+        let result = match record.result {
+            GameResult::WhiteWins => 1.0,
+            GameResult::BlackWins => 0.0,
+            GameResult::Draw | GameResult::Unfinished => 0.5,
+        };
+        let Ok(positions) = record.replay() else { continue };
+        for (_, gameboard) in positions.iter().skip(2 * options.random_moves as usize) {
+            if gameboard.pending_removals > 0 {
+                continue;
+            }
+            let white = Features::of(gameboard, Player::White).values();
+            let black = Features::of(gameboard, Player::Black).values();
+            let mut features = [0.0; TERM_COUNT];
+            for (feature, (white, black)) in features.iter_mut().zip(white.iter().zip(black.iter())) {
+                *feature = (white - black) as f64;
+            }
+            samples.push(Sample { placing: gameboard.gamephase == Phase::Place, features, result });
+        }
+    }
+    samples
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn to_values(weights: &Weights) -> [f64; 2 * TERM_COUNT] {
+    let mut values = [0.0; 2 * TERM_COUNT];
+    for (value, weight) in values.iter_mut().zip(weights.place.values().into_iter().chain(weights.moving.values())) {
+        *value = weight as f64;
+    }
+    values
+}
+
+fn from_values(values: &[f64; 2 * TERM_COUNT]) -> Weights {
+    let mut rounded = [0; 2 * TERM_COUNT];
+    for (rounded, value) in rounded.iter_mut().zip(values) {
+        *rounded = value.round() as i32;
+    }
+    let mut place = [0; TERM_COUNT];
+    let mut moving = [0; TERM_COUNT];
+    place.copy_from_slice(&rounded[..TERM_COUNT]);
+    moving.copy_from_slice(&rounded[TERM_COUNT..]);
+    Weights { place: PhaseWeights::from_values(place), moving: PhaseWeights::from_values(moving) }
+}
+
+// mean squared difference between the predicted and the real results
+pub fn prediction_error(samples: &[Sample], weights: &Weights, scale: f64) -> f64 {
+    let values = to_values(weights);
+    samples.iter().map(|sample| (sample.prediction(&values, scale) - sample.result).powi(2)).sum::<f64>() / samples.len().max(1) as f64
+}
+
+// the scale turning evaluations into winning chances that fits the weights best, searched between 10 and 2000
+pub fn fit_scale(samples: &[Sample], weights: &Weights) -> f64 {
+    let (mut low, mut high) = (10.0_f64, 2000.0_f64);
+    //the error is unimodal in the scale, a golden section search finds its minimum
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    for _ in 0..60 {
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+        if prediction_error(samples, weights, left) < prediction_error(samples, weights, right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+    (low + high) / 2.0
+}
+
+// fits the weights to the samples with gradient descent (Adam) on the prediction error, the scale stays fixed
+pub fn tune_weights(samples: &[Sample], weights: &Weights, scale: f64, iterations: u32) -> Weights {
+    const LEARNING_RATE: f64 = 0.5;
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+    let mut values = to_values(weights);
+    let mut momentum = [0.0; 2 * TERM_COUNT];
+    let mut velocity = [0.0; 2 * TERM_COUNT];
+    if samples.is_empty() {
+        return *weights
+    }
+    for step in 1..=iterations {
+        let mut gradient = [0.0; 2 * TERM_COUNT];
+        for sample in samples {
+            let prediction = sample.prediction(&values, scale);
+            let slope = 2.0 * (prediction - sample.result) * prediction * (1.0 - prediction) / scale;
+            let offset = if sample.placing { 0 } else { TERM_COUNT };
+            for (gradient, feature) in gradient[offset..offset + TERM_COUNT].iter_mut().zip(&sample.features) {
+                *gradient += slope * feature;
+            }
+        }
+        for term in 0..2 * TERM_COUNT {
+            let gradient = gradient[term] / samples.len() as f64;
+            momentum[term] = BETA1 * momentum[term] + (1.0 - BETA1) * gradient;
+            velocity[term] = BETA2 * velocity[term] + (1.0 - BETA2) * gradient * gradient;
+            let corrected_momentum = momentum[term] / (1.0 - BETA1.powi(step as i32));
+            let corrected_velocity = velocity[term] / (1.0 - BETA2.powi(step as i32));
+            values[term] -= LEARNING_RATE * corrected_momentum / (corrected_velocity.sqrt() + 1e-8);
+        }
+    }
+    from_values(&values)
+}
+
+// plays the first weights against the second ones, every opening once with each color
+pub fn play_match(first: &Weights, second: &Weights, options: &TuningOptions, mut progress: impl FnMut(u32)) -> MatchResult {
+    let mut result = MatchResult::default();
+    let mut seeds = Rng::new(options.seed ^ 0x5EED);
+    let mut seed = 0;
+    for game in 0..options.match_games {
+        //both games of a pair start with the same random moves
+        if game % 2 == 0 {
+            seed = seeds.next_u64();
+        }
+        let first_is_white = game % 2 == 0;
+        let (white, black) = if first_is_white { (first, second) } else { (second, first) };
+        let winner = match play_game(white, black, options, seed).result() {
+            GameResult::WhiteWins => Some(Player::White),
+            GameResult::BlackWins => Some(Player::Black),
+            GameResult::Draw | GameResult::Unfinished => None,
+        };
+        match winner {
+            None => result.draws += 1,
+            Some(Player::White) if first_is_white => result.wins += 1,
+            Some(Player::Black) if !first_is_white => result.wins += 1,
+            Some(_) => result.losses += 1,
+        }
+        progress(game + 1);
+    }
+    result
+}
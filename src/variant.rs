@@ -0,0 +1,169 @@
+use crate::datastructures::*;
+use std::fmt;
+
+// the board games of the morris family that can be played
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Variant {
+    // one ring and the centre with three stones each, the first mill decides the game
+    Three,
+    // two rings without lines across them
+    Six,
+    #[default]
+    Nine,
+    // like Nine Men's Morris, with diagonal lines through the corners of the rings
+    Twelve,
+}
+
+// fields, lines and mills of the board of a variant.
+// the fields are numbered ring by ring from the inside like on the Nine Men's Morris board:
+// ring r holds the fields (r-1)*8+1 ..= r*8, starting at the upper middle field and going clockwise.
+// the centre of the Three Men's Morris board is the field after its only ring
+pub struct Topology {
+    pub rings: u8,
+    pub field_count: u8,
+    pub all_fields: u32,
+    pub stones: u8,
+    // neighbours of every field as a mask, slot 0 is unused
+    pub neighbours: [u32; 25],
+    pub mills: [u32; 20],
+    pub mill_count: usize,
+    // the mill lines every field is part of, unused slots are 0
+    pub mills_of: [[u32; 4]; 25],
+}
+
+static TOPOLOGIES: [Topology; 4] = [
+    build_topology(Variant::Three),
+    build_topology(Variant::Six),
+    build_topology(Variant::Nine),
+    build_topology(Variant::Twelve),
+];
+
+// the field at the position (0 = upper middle, clockwise) of a ring
+pub const fn ring_field(ring: u8, position: u8) -> u8 {
+    (ring - 1) * 8 + position % 8 + 1
+}
+
+const fn build_topology(variant: Variant) -> Topology {
+    let (rings, stones, centre, diagonals) = match variant {
+        Variant::Three => (1, 3, true, false),
+        Variant::Six => (2, 6, false, false),
+        Variant::Nine => (3, 9, false, false),
+        Variant::Twelve => (3, 12, false, true),
+    };
+    let field_count = rings * 8 + if centre { 1 } else { 0 };
+    let mut topology = Topology {
+        rings,
+        field_count,
+        all_fields: (1 << field_count) - 1,
+        stones,
+        neighbours: [0; 25],
+        mills: [0; 20],
+        mill_count: 0,
+        mills_of: [[0; 4]; 25],
+    };
+    let mut ring: u8 = 1;
+    while ring <= rings {
+        let mut position: u8 = 0;
+        while position < 8 {
+            let index = ring_field(ring, position) as usize;
+            topology.neighbours[index] |= field_bit(ring_field(ring, position + 1)) | field_bit(ring_field(ring, position + 7));
+            //middle fields are connected to the neighbouring rings, corners only by the diagonals
+            if ring < rings && (position.is_multiple_of(2) || diagonals) {
+                let outer = ring_field(ring + 1, position);
+                topology.neighbours[index] |= field_bit(outer);
+                topology.neighbours[outer as usize] |= field_bit(index as u8);
+            }
+            //the centre is connected to every field of the ring
+            if centre {
+                topology.neighbours[index] |= field_bit(field_count);
+                topology.neighbours[field_count as usize] |= field_bit(index as u8);
+            }
+            //mills along the sides of the ring, each spanning corner-middle-corner
+            if position.is_multiple_of(2) {
+                topology.mills[topology.mill_count] = field_bit(ring_field(ring, position + 7))
+                    | field_bit(ring_field(ring, position))
+                    | field_bit(ring_field(ring, position + 1));
+                topology.mill_count += 1;
+            }
+            position += 1;
+        }
+        ring += 1;
+    }
+    //mills crossing all three rings through the middle fields, and through the corners along the diagonals
+    let mut position: u8 = 0;
+    while position < 8 {
+        if rings == 3 && (position.is_multiple_of(2) || diagonals) {
+            topology.mills[topology.mill_count] = field_bit(ring_field(1, position)) | field_bit(ring_field(2, position)) | field_bit(ring_field(3, position));
+            topology.mill_count += 1;
+        }
+        //lines through the centre
+        if centre && position < 4 {
+            topology.mills[topology.mill_count] = field_bit(ring_field(1, position)) | field_bit(field_count) | field_bit(ring_field(1, position + 4));
+            topology.mill_count += 1;
+        }
+        position += 1;
+    }
+    let mut mill = 0;
+    while mill < topology.mill_count {
+        let mut index: u8 = 1;
+        while index <= field_count {
+            if topology.mills[mill] & field_bit(index) != 0 {
+                let mut slot = 0;
+                while topology.mills_of[index as usize][slot] != 0 {
+                    slot += 1;
+                }
+                topology.mills_of[index as usize][slot] = topology.mills[mill];
+            }
+            index += 1;
+        }
+        mill += 1;
+    }
+    topology
+}
+
+impl Variant {
+    pub const ALL: [Variant; 4] = [Variant::Three, Variant::Six, Variant::Nine, Variant::Twelve];
+
+    pub const fn topology(self) -> &'static Topology {
+        &TOPOLOGIES[self as usize]
+    }
+
+    pub fn stones(self) -> u8 {
+        self.topology().stones
+    }
+
+    pub fn field_count(self) -> u8 {
+        self.topology().field_count
+    }
+
+    pub fn all_fields(self) -> u32 {
+        self.topology().all_fields
+    }
+
+    // the name used on the command line and in position strings
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Variant::Three => "three",
+            Variant::Six => "six",
+            Variant::Nine => "nine",
+            Variant::Twelve => "twelve",
+        }
+    }
+
+    // accepts the short name as well as the full one, e.g. "twelve" and "Twelve Men's Morris"
+    pub fn from_name(name: &str) -> Option<Variant> {
+        Variant::ALL.into_iter().find(|variant| name.eq_ignore_ascii_case(variant.short_name()) || name.eq_ignore_ascii_case(&variant.to_string()))
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Variant::Three => "Three Men's Morris",
+            Variant::Six => "Six Men's Morris",
+            Variant::Nine => "Nine Men's Morris",
+            Variant::Twelve => "Twelve Men's Morris",
+        };
+        write!(f, "{}", name)
+    }
+}